@@ -1,8 +1,10 @@
-
 use avian3d::prelude::{Collider, RigidBody};
 use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::{definition::{BeaconDefinition, CurrentArena}, flow_field::FlowFieldTarget, ArenaProp, Obstacle}, assets::BeaconAssets, enemy::{siege::SiegeSlots, threat::{TargetKind, Targetable}}, util::{obstacle_layer, Health, SceneRootWithAnimation}, GameState, RunOutcome};
 
-use crate::{arena::{ArenaProp, NavmeshQuery, Obstacle}, assets::BeaconAssets, util::{GameInit, Health, SceneRootWithAnimation}, GameState};
+pub const DEFAULT_BEACON_HEALTH: f32 = 1000.0;
 
 //==============================================================================================
 //        Beacon Plugin
@@ -13,38 +15,44 @@ pub struct BeaconPlugin;
 impl Plugin for BeaconPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnEnter(GameState::InGame), spawn_beacon.in_set(GameInit))
+            .add_event::<BeaconEvent>()
+            .add_systems(Update, (destroy_beacons, check_lose_condition).chain().run_if(in_state(GameState::InGame)))
         ;
     }
 }
 
 //==============================================================================================
-//        Spawn Beacon System
+//        Spawn Beacon
 //==============================================================================================
 
-
+/// Beacons are part of the arena, `build_arena` spawns one for every beacon in the definition.
 pub fn spawn_beacon(
-    mut commands : Commands,
-    mut graphs : ResMut<Assets<AnimationGraph>>,
-    assets: Res<BeaconAssets>,
-) {
+    commands : &mut Commands,
+    graphs : &mut Assets<AnimationGraph>,
+    assets : &BeaconAssets,
+    definition : &BeaconDefinition,
+) -> Entity {
     
     let (graph, id) = AnimationGraph::from_clip(assets.animation.clone());
     
     commands.spawn((
         Name::new("Beacon"),
         Beacon,
+        FlowFieldTarget,
         ArenaProp,
         Obstacle,
         Collider::cuboid(1.0, 4.0, 1.0),
+        obstacle_layer(),
         RigidBody::Static,
-        Transform::from_rotation(Quat::from_rotation_y(-45.0_f32.to_radians())),
-        Health::new(1000.0),
+        Transform::from_xyz(definition.position.x, 0.0, definition.position.y).with_rotation(Quat::from_rotation_y(definition.rotation.to_radians())),
+        Health::new(definition.health),
+        SiegeSlots::default(),
+        Targetable::new(TargetKind::Beacon),
         SceneRootWithAnimation::new(assets.beacon.clone())
             .with_animation_graph(graphs.add(graph))
             .with_animation(id)
             .repeat()
-    ));
+    )).id()
 }
 
 //==============================================================================================
@@ -54,44 +62,103 @@ pub fn spawn_beacon(
 #[derive(Component)]
 pub struct Beacon;
 
+/// A beacon that ran out of health. It stays in the arena as an obstacle, but enemies stop
+/// going after it.
+#[derive(Component)]
+pub struct DestroyedBeacon;
+
+/// When the run is lost, set per arena in its [`ArenaDefinition`](super::definition::ArenaDefinition).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoseCondition {
+    /// Every beacon has to survive.
+    #[default]
+    AnyBeaconLost,
+    /// The run goes on as long as one beacon is standing.
+    AllBeaconsLost,
+}
+
 //==============================================================================================
-//        Beceaon Util
+//        Beacon Events
 //==============================================================================================
 
-const CLOSEST_POINT_RADIUS_DEFAULT: f32 = 2.0;
-
-#[derive(SystemParam)]
-pub struct BeaconQuery<'w> {
-    pub beacon: Single<'w, (&'static Transform, &'static mut Health), With<Beacon>>,
+/// `AllDestroyed` is sent along with the `Destroyed` of the last beacon standing.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum BeaconEvent {
+    Destroyed { beacon : Entity },
+    AllDestroyed,
 }
 
-impl<'w> BeaconQuery<'w> {
-    
-    pub fn closest_point(&self, other : &Transform, range : f32) -> Vec2 {
-        let other = other.translation.xz();
-        let beacon = self.beacon.0.translation.xz();
-        
-        let inbetween = -(beacon - other).normalize();
-        let closest = inbetween * range;
-        
-        closest
+//==============================================================================================
+//        Beacon Systems
+//==============================================================================================
+
+pub fn destroy_beacons(
+    mut commands : Commands,
+    beacons : Query<(Entity, &Health), (With<Beacon>, Without<DestroyedBeacon>)>,
+    mut beacon_events : EventWriter<BeaconEvent>,
+) {
+    let mut remaining = beacons.iter().count();
+    for (entity, health) in beacons.iter() {
+        if health.current_health > 0.0 { continue }
+        commands.entity(entity)
+            .insert(DestroyedBeacon)
+            .remove::<(Targetable, FlowFieldTarget)>();
+        beacon_events.write(BeaconEvent::Destroyed { beacon: entity });
+        remaining -= 1;
+        if remaining == 0 {
+            beacon_events.write(BeaconEvent::AllDestroyed);
+        }
     }
+}
 
-    pub fn towards_beacon(&self, other : &Transform) -> Dir3 {
-        let direction = (other.translation.xz() - self.beacon.0.translation.xz()).normalize_or_zero();
-        Dir3::from_xyz_unchecked(direction.x, 0.0, direction.y)
+pub fn check_lose_condition(
+    mut commands : Commands,
+    mut beacon_events : EventReader<BeaconEvent>,
+    arena : Res<CurrentArena>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    let lost = beacon_events.read().any(|event| match arena.lose_condition {
+        LoseCondition::AnyBeaconLost => matches!(event, BeaconEvent::Destroyed { .. }),
+        LoseCondition::AllBeaconsLost => matches!(event, BeaconEvent::AllDestroyed),
+    });
+    if lost {
+        info!("The beacons have fallen");
+        commands.insert_resource(RunOutcome::Lost);
+        next_state.set(GameState::GameOver);
     }
+}
 
-    pub fn take_damage(&mut self, damage : f32) {
-        self.beacon.1.take_damage(damage);
+//==============================================================================================
+//        Beceaon Util
+//==============================================================================================
+
+/// The beacons that are still standing.
+#[derive(SystemParam)]
+pub struct BeaconQuery<'w, 's> {
+    pub beacons : Query<'w, 's, (Entity, &'static Transform, &'static Health, &'static SiegeSlots), (With<Beacon>, Without<DestroyedBeacon>)>,
+}
+
+impl<'w, 's> BeaconQuery<'w, 's> {
+    
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        self.beacons.iter().map(|(entity, transform, ..)| (entity, transform.translation.xz()))
+    }
+    
+    pub fn nearest(&self, position : Vec2) -> Option<Entity> {
+        self.iter()
+            .min_by(|(_, a), (_, b)| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+            .map(|(entity, _)| entity)
     }
     
-    pub fn within_range(&self, other : &Transform, range : f32) -> bool {
-        let other = other.translation.xz();
-        let beacon = self.beacon.0.translation.xz();
-        
-        let distance = (beacon - other).length();
-        
-        distance <= range
+    /// The beacon with the most enemies besieging it, the one with the least health left breaks ties.
+    /// Beacons nobody has touched yet aren't threatened at all.
+    pub fn most_threatened(&self) -> Option<Entity> {
+        self.beacons.iter()
+            .filter(|(_, _, health, siege)| siege.besieger_count() > 0 || health.current_health < health.max_health)
+            .max_by(|(_, _, health_a, siege_a), (_, _, health_b, siege_b)| {
+                siege_a.besieger_count().cmp(&siege_b.besieger_count())
+                    .then(health_b.current_health.total_cmp(&health_a.current_health))
+            })
+            .map(|(entity, ..)| entity)
     }
 }
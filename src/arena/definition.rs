@@ -0,0 +1,261 @@
+use avian3d::prelude::Collider;
+use bevy::{asset::{io::Reader, AssetLoader, LoadContext, LoadState}, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::{beacon::{LoseCondition, DEFAULT_BEACON_HEALTH}, generator::ArenaTheme, portal::WinCondition, PatrolWaypoint}, GameState};
+
+const DEFAULT_ARENA_PATH: &str = "arenas/default.arena.ron";
+const BUILT_IN_ARENA: &str = include_str!("../../assets/arenas/default.arena.ron");
+
+//==============================================================================================
+//        Arena Definition Plugin
+//==============================================================================================
+
+pub struct ArenaDefinitionPlugin;
+
+impl Plugin for ArenaDefinitionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<ArenaDefinition>()
+            .register_asset_loader(ArenaDefinitionLoader)
+            .add_systems(Startup, load_arena_file)
+            .add_systems(Update, select_arena.run_if(in_state(GameState::LoadingArena)))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Arena Definition
+//==============================================================================================
+
+/// Everything `build_arena` needs to lay out an arena, loaded from `.arena.ron` files. The arena
+/// is a rectangle of `size` centered on the origin.
+#[derive(Asset, TypePath, Clone, Debug, Serialize, Deserialize)]
+pub struct ArenaDefinition {
+    pub name : String,
+    pub size : Vec2,
+    #[serde(default)]
+    pub ground : GroundDefinition,
+    #[serde(default)]
+    pub lighting : LightingDefinition,
+    /// Where the player starts, over the ground.
+    pub player_start : Vec2,
+    pub beacons : Vec<BeaconDefinition>,
+    #[serde(default)]
+    pub lose_condition : LoseCondition,
+    #[serde(default)]
+    pub win_condition : WinCondition,
+    #[serde(default)]
+    pub spawn_zones : Vec<SpawnZoneDefinition>,
+    #[serde(default)]
+    pub patrol_routes : Vec<PatrolRouteDefinition>,
+    #[serde(default)]
+    pub portals : Vec<PortalDefinition>,
+    #[serde(default)]
+    pub obstacles : Vec<ObstacleDefinition>,
+    /// Whether the generator fills the rest of the arena with obstacles, see [`ArenaSettings`](super::generator::ArenaSettings).
+    #[serde(default)]
+    pub procedural : bool,
+}
+
+impl ArenaDefinition {
+    /// The default arena as it was when the game was built, used when the file can't be loaded.
+    pub fn built_in() -> Self {
+        ron::de::from_str(BUILT_IN_ARENA).expect("The built-in arena definition is broken, fix assets/arenas/default.arena.ron")
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        self.size / 2.0
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroundDefinition {
+    /// The ground takes the color of the generator's theme when there is none.
+    pub color : Option<(f32, f32, f32)>,
+    pub roughness : f32,
+}
+
+impl Default for GroundDefinition {
+    fn default() -> Self {
+        GroundDefinition { color: None, roughness: 0.9 }
+    }
+}
+
+impl GroundDefinition {
+    pub fn material(&self, theme : ArenaTheme) -> StandardMaterial {
+        StandardMaterial {
+            base_color : self.color.map(|(r, g, b)| Color::srgb(r, g, b)).unwrap_or(theme.ground_color()),
+            perceptual_roughness : self.roughness,
+            ..default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightingDefinition {
+    pub sun_color : (f32, f32, f32),
+    pub sun_illuminance : f32,
+    /// The rotation of the sun as XYZ euler angles in radians.
+    pub sun_rotation : Vec3,
+    pub ambient_brightness : f32,
+}
+
+impl Default for LightingDefinition {
+    fn default() -> Self {
+        LightingDefinition {
+            sun_color : (1.0, 1.0, 1.0),
+            sun_illuminance : light_consts::lux::AMBIENT_DAYLIGHT,
+            sun_rotation : Vec3::new(5.5, 1.0, 0.0),
+            ambient_brightness : 80.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BeaconDefinition {
+    pub position : Vec2,
+    /// The rotation around the up axis in degrees.
+    #[serde(default)]
+    pub rotation : f32,
+    #[serde(default = "default_beacon_health")]
+    pub health : f32,
+}
+
+fn default_beacon_health() -> f32 {
+    DEFAULT_BEACON_HEALTH
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpawnZoneDefinition {
+    pub name : String,
+    pub position : Vec2,
+    pub radius : f32,
+}
+
+/// The waypoints of a route in the order they are walked, see [`PatrolWaypoint`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PatrolRouteDefinition {
+    pub name : String,
+    pub points : Vec<Vec2>,
+}
+
+impl PatrolRouteDefinition {
+    pub fn waypoints(&self) -> impl Iterator<Item = (PatrolWaypoint, Vec2)> + '_ {
+        self.points.iter().enumerate().map(|(order, point)| (PatrolWaypoint::new(self.name.clone(), order as u32), *point))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PortalDefinition {
+    pub position : Vec2,
+    pub enemies_per_spawn : Option<u32>,
+    /// Seconds between spawns.
+    pub interval : Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ShapeDefinition {
+    Cuboid { size : Vec3 },
+    Cylinder { radius : f32, height : f32 },
+    Sphere { radius : f32 },
+}
+
+impl ShapeDefinition {
+    pub fn collider(&self) -> Collider {
+        match *self {
+            ShapeDefinition::Cuboid { size } => Collider::cuboid(size.x, size.y, size.z),
+            ShapeDefinition::Cylinder { radius, height } => Collider::cylinder(radius, height),
+            ShapeDefinition::Sphere { radius } => Collider::sphere(radius),
+        }
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        match *self {
+            ShapeDefinition::Cuboid { size } => Cuboid::from_size(size).into(),
+            ShapeDefinition::Cylinder { radius, height } => Cylinder::new(radius, height).into(),
+            ShapeDefinition::Sphere { radius } => Sphere::new(radius).into(),
+        }
+    }
+
+    /// The radius of a circle around the footprint of the shape.
+    pub fn radius(&self) -> f32 {
+        match *self {
+            ShapeDefinition::Cuboid { size } => size.xz().length() * 0.5,
+            ShapeDefinition::Cylinder { radius, .. } | ShapeDefinition::Sphere { radius } => radius,
+        }
+    }
+}
+
+/// A hand placed obstacle. The collider is centered on `position`, which is also where the model
+/// is put. Without a model the shape of the collider is drawn instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObstacleDefinition {
+    pub position : Vec3,
+    /// The rotation around the up axis in degrees.
+    #[serde(default)]
+    pub rotation : f32,
+    pub shape : ShapeDefinition,
+    /// The path of a scene, like `models/rock.glb#Scene0`.
+    pub model : Option<String>,
+    pub color : Option<(f32, f32, f32)>,
+}
+
+//==============================================================================================
+//        Loading
+//==============================================================================================
+
+#[derive(Default)]
+pub struct ArenaDefinitionLoader;
+
+impl AssetLoader for ArenaDefinitionLoader {
+    type Asset = ArenaDefinition;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(&self, reader : &mut dyn Reader, _settings : &(), _load_context : &mut LoadContext<'_>) -> Result<ArenaDefinition, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["arena.ron"]
+    }
+}
+
+/// The arena that is built whenever the game starts.
+#[derive(Resource, Clone, Debug, Deref, DerefMut)]
+pub struct CurrentArena(pub ArenaDefinition);
+
+/// The file the arena is loaded from. It is kept out of the asset collections, so a missing or
+/// broken file falls back to the built-in arena instead of holding up loading.
+#[derive(Resource)]
+pub struct ArenaFile(pub Handle<ArenaDefinition>);
+
+pub fn load_arena_file(
+    mut commands : Commands,
+    asset_server : Res<AssetServer>,
+) {
+    commands.insert_resource(ArenaFile(asset_server.load(DEFAULT_ARENA_PATH)));
+}
+
+/// Waits for the arena file to load or fail, then starts the game.
+pub fn select_arena(
+    mut commands : Commands,
+    file : Res<ArenaFile>,
+    asset_server : Res<AssetServer>,
+    definitions : Res<Assets<ArenaDefinition>>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    let definition = match asset_server.load_state(&file.0) {
+        LoadState::Loaded => definitions.get(&file.0).cloned().unwrap_or_else(ArenaDefinition::built_in),
+        LoadState::Failed(error) => {
+            error!("The arena failed to load, falling back to the built-in arena: {error}");
+            ArenaDefinition::built_in()
+        }
+        _ => return,
+    };
+    commands.insert_resource(CurrentArena(definition));
+    next_state.set(GameState::InGame);
+}
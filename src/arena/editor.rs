@@ -0,0 +1,535 @@
+use std::{collections::BTreeMap, env, f32::consts::FRAC_PI_2, path::{Path, PathBuf}};
+
+use bevy::{color::palettes, prelude::*};
+use vleue_navigator::{prelude::ManagedNavMesh, NavMeshDebug};
+
+use crate::{arena::{beacon::{spawn_beacon, Beacon, DEFAULT_BEACON_HEALTH}, definition::{ArenaDefinition, ArenaFile, BeaconDefinition, CurrentArena, ObstacleDefinition, PatrolRouteDefinition, PortalDefinition, ShapeDefinition, SpawnZoneDefinition}, portal::Portal, spawn_obstacle, spawn_patrol_waypoint, spawn_portal, spawn_spawn_zone, Ground, PatrolWaypoint, PlacedObstacle, SpawnZone, NAVMESH_DEBUG_COLOR}, assets::BeaconAssets, camera::{CameraFocus, MainCamera}, util::Health, GameState};
+
+const EDITOR_TOGGLE_KEY: KeyCode = KeyCode::F2;
+const ROTATION_STEP: f32 = 15.0;
+const RADIUS_STEP: f32 = 0.5;
+const MIN_ZONE_RADIUS: f32 = 1.0;
+const DEFAULT_ZONE_RADIUS: f32 = 4.0;
+const CAMERA_PAN_SPEED: f32 = 15.0;
+const HANDLE_RADIUS: f32 = 0.5;
+/// The folder the asset server reads from, relative to the base path it resolves, see [`asset_root`].
+const ASSET_ROOT: &str = "assets";
+
+//==============================================================================================
+//        Arena Editor Plugin
+//==============================================================================================
+
+/// A debug only editor for the current arena. Press F2 to pause the game and start editing.
+///
+/// - Left click selects, drag to move the selection.
+/// - Right click places with the current tool, picked with the number keys 1 to 6.
+/// - Q and E rotate the selection, - and = resize a selected spawn zone.
+/// - Delete or Backspace removes the selection.
+/// - WASD pans the camera.
+/// - Ctrl+S saves the arena back to its `.arena.ron` file.
+pub struct ArenaEditorPlugin;
+
+impl Plugin for ArenaEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ArenaEditor>()
+            .init_resource::<EditorHandleAssets>()
+            .add_observer(select_on_press)
+            .add_observer(start_drag)
+            .add_observer(drag_selected)
+            .add_observer(place_on_click)
+            .add_systems(Update, toggle_editor.run_if(in_state(GameState::InGame)))
+            .add_systems(Update, (
+                add_editor_handles,
+                pick_tool,
+                edit_selected,
+                pan_camera,
+                save_arena,
+                draw_editor_gizmos,
+            ).run_if(in_state(GameState::InGame).and(is_editing)))
+            .add_systems(OnExit(GameState::InGame), stop_editing)
+        ;
+    }
+}
+
+//==============================================================================================
+//        Editor State
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditorTool {
+    #[default]
+    CuboidObstacle,
+    PillarObstacle,
+    SpawnZone,
+    /// Inserted right after the selected waypoint, or starts a new route.
+    PatrolWaypoint,
+    Beacon,
+    Portal,
+}
+
+impl EditorTool {
+    const KEYS : [(KeyCode, EditorTool); 6] = [
+        (KeyCode::Digit1, EditorTool::CuboidObstacle),
+        (KeyCode::Digit2, EditorTool::PillarObstacle),
+        (KeyCode::Digit3, EditorTool::SpawnZone),
+        (KeyCode::Digit4, EditorTool::PatrolWaypoint),
+        (KeyCode::Digit5, EditorTool::Beacon),
+        (KeyCode::Digit6, EditorTool::Portal),
+    ];
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ArenaEditor {
+    pub enabled : bool,
+    pub tool : EditorTool,
+    pub selected : Option<Entity>,
+    /// Keeps the selection from jumping to the cursor when a drag starts off center.
+    drag_offset : Vec2,
+}
+
+/// Anything the editor can select, move and delete.
+type Editable = Or<(With<PlacedObstacle>, With<SpawnZone>, With<PatrolWaypoint>, With<Beacon>, With<Portal>)>;
+
+/// Spawn zones and waypoints have no mesh of their own, this gives the picking backend something to hit.
+#[derive(Component)]
+pub struct EditorHandle;
+
+#[derive(Component)]
+pub struct HasEditorHandle;
+
+#[derive(Resource)]
+pub struct EditorHandleAssets {
+    mesh : Handle<Mesh>,
+    material : Handle<StandardMaterial>,
+}
+
+impl FromWorld for EditorHandleAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Cylinder::new(HANDLE_RADIUS, 0.1));
+        let material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+            base_color : Color::srgba(1.0, 1.0, 1.0, 0.5),
+            alpha_mode : AlphaMode::Blend,
+            unlit : true,
+            ..default()
+        });
+        EditorHandleAssets { mesh, material }
+    }
+}
+
+pub fn is_editing(editor : Option<Res<ArenaEditor>>) -> bool {
+    editor.is_some_and(|editor| editor.enabled)
+}
+
+//==============================================================================================
+//        Toggling
+//==============================================================================================
+
+pub fn toggle_editor(
+    mut commands : Commands,
+    keys : Res<ButtonInput<KeyCode>>,
+    mut editor : ResMut<ArenaEditor>,
+    mut time : ResMut<Time<Virtual>>,
+    navmeshes : Query<Entity, With<ManagedNavMesh>>,
+    handles : Query<Entity, With<EditorHandle>>,
+    handled : Query<Entity, With<HasEditorHandle>>,
+) {
+    if !keys.just_pressed(EDITOR_TOGGLE_KEY) { return }
+    editor.enabled = !editor.enabled;
+
+    let navmesh_color = if editor.enabled { palettes::tailwind::LIME_400 } else { NAVMESH_DEBUG_COLOR };
+    for navmesh in navmeshes.iter() {
+        commands.entity(navmesh).insert(NavMeshDebug(navmesh_color.into()));
+    }
+
+    if editor.enabled {
+        time.pause();
+        info!("Arena editor on, tool {:?}", editor.tool);
+    } else {
+        time.unpause();
+        editor.selected = None;
+        remove_editor_handles(&mut commands, &handles, &handled);
+        info!("Arena editor off");
+    }
+}
+
+/// Leaving the game while editing would otherwise keep it paused.
+pub fn stop_editing(
+    mut commands : Commands,
+    mut editor : ResMut<ArenaEditor>,
+    mut time : ResMut<Time<Virtual>>,
+    handles : Query<Entity, With<EditorHandle>>,
+    handled : Query<Entity, With<HasEditorHandle>>,
+) {
+    if !editor.enabled { return }
+    editor.enabled = false;
+    editor.selected = None;
+    time.unpause();
+    remove_editor_handles(&mut commands, &handles, &handled);
+}
+
+fn remove_editor_handles(
+    commands : &mut Commands,
+    handles : &Query<Entity, With<EditorHandle>>,
+    handled : &Query<Entity, With<HasEditorHandle>>,
+) {
+    for handle in handles.iter() {
+        commands.entity(handle).despawn();
+    }
+    for entity in handled.iter() {
+        commands.entity(entity).remove::<HasEditorHandle>();
+    }
+}
+
+pub fn add_editor_handles(
+    mut commands : Commands,
+    assets : Res<EditorHandleAssets>,
+    unhandled : Query<Entity, (Or<(With<SpawnZone>, With<PatrolWaypoint>)>, Without<HasEditorHandle>)>,
+) {
+    for entity in unhandled.iter() {
+        commands.entity(entity)
+            .insert((HasEditorHandle, Visibility::default()))
+            .with_child((
+                Name::new("Editor Handle"),
+                EditorHandle,
+                Mesh3d(assets.mesh.clone()),
+                MeshMaterial3d(assets.material.clone()),
+                Transform::from_xyz(0.0, 0.05, 0.0),
+            ));
+    }
+}
+
+//==============================================================================================
+//        Picking
+//==============================================================================================
+
+/// Pointer events bubble up from the mesh that was hit, so the first editable entity on the way
+/// up is the one that gets selected.
+pub fn select_on_press(
+    mut trigger : Trigger<Pointer<Pressed>>,
+    mut editor : ResMut<ArenaEditor>,
+    editables : Query<(), Editable>,
+    grounds : Query<(), With<Ground>>,
+) {
+    if !editor.enabled || trigger.button != PointerButton::Primary { return }
+    let target = trigger.target();
+    if editables.contains(target) {
+        editor.selected = Some(target);
+        trigger.propagate(false);
+    } else if grounds.contains(target) {
+        editor.selected = None;
+    }
+}
+
+pub fn start_drag(
+    trigger : Trigger<Pointer<DragStart>>,
+    mut editor : ResMut<ArenaEditor>,
+    transforms : Query<&Transform, Editable>,
+    camera : Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    if !editor.enabled || trigger.button != PointerButton::Primary || editor.selected != Some(trigger.target()) { return }
+    let Ok(transform) = transforms.get(trigger.target()) else { return };
+    let Some(point) = ground_point(&camera, trigger.pointer_location.position) else { return };
+    editor.drag_offset = transform.translation.xz() - point;
+}
+
+pub fn drag_selected(
+    mut trigger : Trigger<Pointer<Drag>>,
+    editor : Res<ArenaEditor>,
+    mut transforms : Query<&mut Transform, Editable>,
+    camera : Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    if !editor.enabled || trigger.button != PointerButton::Primary || editor.selected != Some(trigger.target()) { return }
+    let Ok(mut transform) = transforms.get_mut(trigger.target()) else { return };
+    let Some(point) = ground_point(&camera, trigger.pointer_location.position) else { return };
+    let position = point + editor.drag_offset;
+    transform.translation.x = position.x;
+    transform.translation.z = position.y;
+    trigger.propagate(false);
+}
+
+/// Where the cursor is over the ground plane.
+fn ground_point((camera, camera_transform) : &(&Camera, &GlobalTransform), cursor : Vec2) -> Option<Vec2> {
+    let ray = camera.viewport_to_world(camera_transform, cursor).ok()?;
+    let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+    Some(ray.get_point(distance).xz())
+}
+
+//==============================================================================================
+//        Placing
+//==============================================================================================
+
+pub fn place_on_click(
+    mut trigger : Trigger<Pointer<Click>>,
+    mut commands : Commands,
+    mut editor : ResMut<ArenaEditor>,
+    mut meshes : ResMut<Assets<Mesh>>,
+    mut materials : ResMut<Assets<StandardMaterial>>,
+    mut graphs : ResMut<Assets<AnimationGraph>>,
+    beacon_assets : Res<BeaconAssets>,
+    asset_server : Res<AssetServer>,
+    zones : Query<&SpawnZone>,
+    mut waypoints : Query<(Entity, &mut PatrolWaypoint)>,
+) {
+    if !editor.enabled || trigger.button != PointerButton::Secondary { return }
+    let Some(hit) = trigger.hit.position else { return };
+    trigger.propagate(false);
+    let position = hit.xz();
+
+    let placed = match editor.tool {
+        EditorTool::CuboidObstacle => Some(spawn_obstacle(&mut commands, &mut meshes, &mut materials, &asset_server, &ObstacleDefinition {
+            position : Vec3::new(position.x, 1.0, position.y),
+            rotation : 0.0,
+            shape : ShapeDefinition::Cuboid { size: Vec3::splat(2.0) },
+            model : None,
+            color : None,
+        })),
+        EditorTool::PillarObstacle => Some(spawn_obstacle(&mut commands, &mut meshes, &mut materials, &asset_server, &ObstacleDefinition {
+            position : Vec3::new(position.x, 2.0, position.y),
+            rotation : 0.0,
+            shape : ShapeDefinition::Cylinder { radius: 0.6, height: 4.0 },
+            model : None,
+            color : None,
+        })),
+        EditorTool::SpawnZone => {
+            let name = (1..)
+                .map(|index| format!("zone {index}"))
+                .find(|name| zones.iter().all(|zone| zone.name != *name))
+                .unwrap_or_default();
+            Some(spawn_spawn_zone(&mut commands, &SpawnZoneDefinition { name, position, radius: DEFAULT_ZONE_RADIUS }))
+        }
+        EditorTool::PatrolWaypoint => {
+            let previous = editor.selected.and_then(|selected| waypoints.get(selected).ok()).map(|(_, waypoint)| waypoint.clone());
+            let waypoint = match previous {
+                Some(previous) => {
+                    // Make room after the selected waypoint.
+                    for (_, mut waypoint) in waypoints.iter_mut() {
+                        if waypoint.route == previous.route && waypoint.order > previous.order {
+                            waypoint.order += 1;
+                        }
+                    }
+                    PatrolWaypoint::new(previous.route, previous.order + 1)
+                }
+                None => {
+                    let route = (1..)
+                        .map(|index| format!("route {index}"))
+                        .find(|route| waypoints.iter().all(|(_, waypoint)| waypoint.route != *route))
+                        .unwrap_or_default();
+                    PatrolWaypoint::new(route, 0)
+                }
+            };
+            Some(spawn_patrol_waypoint(&mut commands, waypoint, position))
+        }
+        EditorTool::Beacon => Some(spawn_beacon(&mut commands, &mut graphs, &beacon_assets, &BeaconDefinition { position, rotation: 0.0, health: DEFAULT_BEACON_HEALTH })),
+        EditorTool::Portal => Some(spawn_portal(&mut commands, &PortalDefinition { position, enemies_per_spawn: None, interval: None })),
+    };
+
+    if placed.is_some() {
+        editor.selected = placed;
+    }
+}
+
+//==============================================================================================
+//        Editing
+//==============================================================================================
+
+pub fn pick_tool(
+    keys : Res<ButtonInput<KeyCode>>,
+    mut editor : ResMut<ArenaEditor>,
+) {
+    for (key, tool) in EditorTool::KEYS {
+        if keys.just_pressed(key) {
+            editor.tool = tool;
+            info!("Arena editor tool {tool:?}");
+        }
+    }
+}
+
+pub fn edit_selected(
+    mut commands : Commands,
+    keys : Res<ButtonInput<KeyCode>>,
+    mut editor : ResMut<ArenaEditor>,
+    mut transforms : Query<&mut Transform, Editable>,
+    mut zones : Query<&mut SpawnZone>,
+) {
+    let Some(selected) = editor.selected else { return };
+    let Ok(mut transform) = transforms.get_mut(selected) else {
+        editor.selected = None;
+        return;
+    };
+
+    if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        commands.entity(selected).despawn();
+        editor.selected = None;
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyQ) {
+        transform.rotate_y(ROTATION_STEP.to_radians());
+    }
+    if keys.just_pressed(KeyCode::KeyE) {
+        transform.rotate_y(-ROTATION_STEP.to_radians());
+    }
+
+    if let Ok(mut zone) = zones.get_mut(selected) {
+        if keys.just_pressed(KeyCode::Minus) {
+            zone.radius = (zone.radius - RADIUS_STEP).max(MIN_ZONE_RADIUS);
+        }
+        if keys.just_pressed(KeyCode::Equal) {
+            zone.radius += RADIUS_STEP;
+        }
+    }
+}
+
+/// The game is paused while editing, so the camera is moved by hand instead of following the player.
+pub fn pan_camera(
+    keys : Res<ButtonInput<KeyCode>>,
+    focus : Single<(&mut Transform, &CameraFocus)>,
+    time : Res<Time<Real>>,
+) {
+    let (mut transform, focus) = focus.into_inner();
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) { return }
+
+    let mut direction = Vec3::ZERO;
+    if keys.pressed(KeyCode::KeyW) { direction.z -= 1.0 }
+    if keys.pressed(KeyCode::KeyS) { direction.z += 1.0 }
+    if keys.pressed(KeyCode::KeyA) { direction.x -= 1.0 }
+    if keys.pressed(KeyCode::KeyD) { direction.x += 1.0 }
+
+    let direction = Quat::from_rotation_y(focus.rotation.to_radians()) * direction.normalize_or_zero();
+    transform.translation += direction * CAMERA_PAN_SPEED * time.delta_secs();
+}
+
+//==============================================================================================
+//        Saving
+//==============================================================================================
+
+/// Writes the arena as it is now over the file it was loaded from. Generated pieces are left out,
+/// they come back from the generator as long as the arena stays procedural.
+pub fn save_arena(
+    keys : Res<ButtonInput<KeyCode>>,
+    mut arena : ResMut<CurrentArena>,
+    arena_file : Res<ArenaFile>,
+    asset_server : Res<AssetServer>,
+    obstacles : Query<(&Transform, &PlacedObstacle)>,
+    zones : Query<(&Transform, &SpawnZone)>,
+    waypoints : Query<(&Transform, &PatrolWaypoint)>,
+    beacons : Query<(&Transform, &Health), With<Beacon>>,
+    portals : Query<(&Transform, &Portal)>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) || !keys.just_pressed(KeyCode::KeyS) { return }
+
+    let mut routes : BTreeMap<String, Vec<(u32, Vec2)>> = BTreeMap::new();
+    for (transform, waypoint) in waypoints.iter() {
+        routes.entry(waypoint.route.clone()).or_default().push((waypoint.order, transform.translation.xz()));
+    }
+
+    let default_portal = Portal::default();
+    let definition = ArenaDefinition {
+        beacons : beacons.iter()
+            .map(|(transform, health)| BeaconDefinition { position: transform.translation.xz(), rotation: yaw_degrees(transform), health: health.max_health })
+            .collect(),
+        spawn_zones : zones.iter()
+            .map(|(transform, zone)| SpawnZoneDefinition { name: zone.name.clone(), position: transform.translation.xz(), radius: zone.radius })
+            .collect(),
+        patrol_routes : routes.into_iter()
+            .map(|(name, mut points)| {
+                points.sort_by_key(|(order, _)| *order);
+                PatrolRouteDefinition { name, points: points.into_iter().map(|(_, point)| point).collect() }
+            })
+            .collect(),
+        portals : portals.iter()
+            .map(|(transform, portal)| PortalDefinition {
+                position : transform.translation.xz(),
+                enemies_per_spawn : (portal.enemies_per_spawn != default_portal.enemies_per_spawn).then_some(portal.enemies_per_spawn),
+                interval : (portal.interval.duration() != default_portal.interval.duration()).then_some(portal.interval.duration().as_secs_f32()),
+            })
+            .collect(),
+        obstacles : obstacles.iter()
+            .map(|(transform, PlacedObstacle(obstacle))| ObstacleDefinition {
+                position : transform.translation,
+                rotation : yaw_degrees(transform),
+                ..obstacle.clone()
+            })
+            .collect(),
+        ..arena.0.clone()
+    };
+
+    if definition.beacons.is_empty() {
+        warn!("Saving arena {} without any beacons", definition.name);
+    }
+
+    let Some(path) = asset_server.get_path(&arena_file.0) else {
+        error!("The arena has no file to save to");
+        return;
+    };
+    let path = asset_root().join(path.path());
+    let saved = ron::ser::to_string_pretty(&definition, ron::ser::PrettyConfig::default())
+        .map_err(|error| error.to_string())
+        .and_then(|text| std::fs::write(&path, text).map_err(|error| error.to_string()));
+    match saved {
+        Ok(()) => info!("Saved arena {} to {}", definition.name, path.display()),
+        Err(error) => error!("Failed to save arena to {}: {error}", path.display()),
+    }
+
+    arena.0 = definition;
+}
+
+/// Resolves the asset folder the way bevy's `FileAssetReader` does, so the arena is written over
+/// the file that was loaded whatever the working directory is.
+fn asset_root() -> PathBuf {
+    env::var("BEVY_ASSET_ROOT")
+        .or_else(|_| env::var("CARGO_MANIFEST_DIR"))
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| env::current_exe().ok().and_then(|path| path.parent().map(Path::to_path_buf)))
+        .unwrap_or_default()
+        .join(ASSET_ROOT)
+}
+
+fn yaw_degrees(transform : &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::YXZ).0.to_degrees()
+}
+
+//==============================================================================================
+//        Gizmos
+//==============================================================================================
+
+pub fn draw_editor_gizmos(
+    mut gizmos : Gizmos,
+    editor : Res<ArenaEditor>,
+    arena : Res<CurrentArena>,
+    obstacles : Query<(Entity, &Transform, &PlacedObstacle)>,
+    zones : Query<(Entity, &Transform, &SpawnZone)>,
+    waypoints : Query<(Entity, &Transform, &PatrolWaypoint)>,
+    beacons : Query<(Entity, &Transform), With<Beacon>>,
+    portals : Query<(Entity, &Transform, &Portal)>,
+) {
+    let flat = |position : Vec3| Isometry3d::new(Vec3::new(position.x, 0.05, position.z), Quat::from_rotation_x(FRAC_PI_2));
+    let mut circles = Vec::new();
+
+    circles.extend(obstacles.iter().map(|(entity, transform, obstacle)| (entity, transform.translation, obstacle.0.shape.radius(), palettes::tailwind::STONE_300)));
+    circles.extend(zones.iter().map(|(entity, transform, zone)| (entity, transform.translation, zone.radius, palettes::tailwind::ORANGE_400)));
+    circles.extend(waypoints.iter().map(|(entity, transform, _)| (entity, transform.translation, HANDLE_RADIUS, palettes::tailwind::SKY_400)));
+    circles.extend(beacons.iter().map(|(entity, transform)| (entity, transform.translation, 1.5, palettes::tailwind::CYAN_300)));
+    circles.extend(portals.iter().map(|(entity, transform, portal)| (entity, transform.translation, portal.spawn_radius, palettes::tailwind::PURPLE_400)));
+
+    for (entity, position, radius, color) in circles {
+        if editor.selected == Some(entity) {
+            gizmos.circle(flat(position), radius + 0.2, palettes::tailwind::YELLOW_300);
+        }
+        gizmos.circle(flat(position), radius, color);
+    }
+
+    let mut routes : BTreeMap<&str, Vec<(u32, Vec3)>> = BTreeMap::new();
+    for (_, transform, waypoint) in waypoints.iter() {
+        routes.entry(waypoint.route.as_str()).or_default().push((waypoint.order, transform.translation.with_y(0.05)));
+    }
+    for mut points in routes.into_values() {
+        points.sort_by_key(|(order, _)| *order);
+        let first = points.first().map(|(_, point)| *point);
+        gizmos.linestrip(points.into_iter().map(|(_, point)| point).chain(first), palettes::tailwind::SKY_400);
+    }
+
+    gizmos.rect(flat(Vec3::ZERO), arena.size, palettes::tailwind::YELLOW_600);
+}
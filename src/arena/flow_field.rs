@@ -0,0 +1,287 @@
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap}};
+
+use bevy::prelude::*;
+
+use crate::{arena::{NavMeshRebuilt, NavmeshQuery}, enemy::DefaultEnemyBehavior, GameState};
+
+pub const FLOW_FIELD_CELL_SIZE: f32 = 0.5;
+
+const NEIGHBORS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+//==============================================================================================
+//        FlowField Plugin
+//==============================================================================================
+
+pub struct FlowFieldPlugin;
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FlowFieldGrid>()
+            .init_resource::<FlowFields>()
+            .add_systems(Update, (rebuild_flow_field_grid, update_flow_fields)
+                .chain()
+                .before(DefaultEnemyBehavior)
+                .run_if(in_state(GameState::InGame))
+            )
+        ;
+    }
+}
+
+//==============================================================================================
+//        FlowField Components
+//==============================================================================================
+
+/// Marks an entity that a lot of enemies will be heading towards. A flow field is kept up to date
+/// for every entity with this component, so enemies can sample it instead of pathfinding on their own.
+#[derive(Component, Default)]
+pub struct FlowFieldTarget;
+
+//==============================================================================================
+//        FlowField Grid
+//==============================================================================================
+
+/// A walkability grid over the whole arena, sampled from the navmesh whenever it is rebuilt.
+#[derive(Resource)]
+pub struct FlowFieldGrid {
+    pub origin : Vec2,
+    pub cell_size : f32,
+    pub size : IVec2,
+    walkable : Vec<bool>,
+    built : bool,
+}
+
+/// Empty until an arena is built, see [`FlowFieldGrid::new`].
+impl Default for FlowFieldGrid {
+    fn default() -> Self {
+        FlowFieldGrid::new(Vec2::ZERO)
+    }
+}
+
+impl FlowFieldGrid {
+    /// A grid covering an arena of this size, centered on the origin.
+    pub fn new(arena_size : Vec2) -> Self {
+        let cells = (arena_size / FLOW_FIELD_CELL_SIZE).ceil().as_ivec2();
+        FlowFieldGrid {
+            origin : -arena_size / 2.0,
+            cell_size : FLOW_FIELD_CELL_SIZE,
+            size : cells,
+            walkable : vec![false; (cells.x * cells.y) as usize],
+            built : false,
+        }
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.built
+    }
+
+    pub fn cell(&self, position : Vec2) -> IVec2 {
+        ((position - self.origin) / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn cell_center(&self, cell : IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * self.cell_size
+    }
+
+    pub fn contains(&self, cell : IVec2) -> bool {
+        cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all()
+    }
+
+    pub fn is_walkable(&self, cell : IVec2) -> bool {
+        self.index(cell).map(|index| self.walkable[index]).unwrap_or(false)
+    }
+
+    fn index(&self, cell : IVec2) -> Option<usize> {
+        if !self.contains(cell) { return None }
+        Some((cell.y * self.size.x + cell.x) as usize)
+    }
+
+    /// Diagonal steps are only allowed when both of the straight steps around the corner are walkable,
+    /// otherwise enemies would try to cut through the corners of obstacles.
+    fn can_step(&self, from : IVec2, offset : IVec2) -> bool {
+        if offset.x != 0 && offset.y != 0 {
+            return self.is_walkable(from + IVec2::new(offset.x, 0)) && self.is_walkable(from + IVec2::new(0, offset.y));
+        }
+        true
+    }
+
+    fn rebuild(&mut self, navmesh : &NavmeshQuery) {
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let cell = IVec2::new(x, y);
+                let walkable = navmesh.is_walkable(self.cell_center(cell));
+                let index = self.index(cell).unwrap();
+                self.walkable[index] = walkable;
+            }
+        }
+        self.built = true;
+    }
+}
+
+//==============================================================================================
+//        FlowField
+//==============================================================================================
+
+/// The integrated distance from every cell of the grid to a single goal, along with the direction
+/// an agent standing in that cell should move to get there.
+pub struct FlowField {
+    pub goal : IVec2,
+    costs : Vec<f32>,
+    directions : Vec<Vec2>,
+}
+
+#[derive(PartialEq)]
+struct OpenCell(f32, IVec2);
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the cheapest cell first.
+        other.0.total_cmp(&self.0)
+    }
+}
+
+impl FlowField {
+    pub fn new(grid : &FlowFieldGrid, goal : IVec2) -> Self {
+        let cell_count = (grid.size.x * grid.size.y) as usize;
+        let mut costs = vec![f32::INFINITY; cell_count];
+        let mut directions = vec![Vec2::ZERO; cell_count];
+        let mut open = BinaryHeap::new();
+
+        if let Some(index) = grid.index(goal) {
+            costs[index] = 0.0;
+            open.push(OpenCell(0.0, goal));
+        }
+
+        while let Some(OpenCell(cost, cell)) = open.pop() {
+            let index = grid.index(cell).unwrap();
+            if cost > costs[index] { continue; }
+            let inside_goal_obstacle = !grid.is_walkable(cell);
+
+            for offset in NEIGHBORS {
+                let next = cell + offset;
+                let Some(next_index) = grid.index(next) else { continue };
+                // Targets like the beacon sit inside an obstacle, so the field is allowed to spread
+                // through the obstacle the goal is in, but never back into one from open ground.
+                if !grid.is_walkable(next) && !inside_goal_obstacle { continue; }
+                if !inside_goal_obstacle && !grid.can_step(cell, offset) { continue; }
+
+                let next_cost = cost + offset.as_vec2().length();
+                if next_cost < costs[next_index] {
+                    costs[next_index] = next_cost;
+                    open.push(OpenCell(next_cost, next));
+                }
+            }
+        }
+
+        for y in 0..grid.size.y {
+            for x in 0..grid.size.x {
+                let cell = IVec2::new(x, y);
+                let index = grid.index(cell).unwrap();
+                if !costs[index].is_finite() || cell == goal { continue; }
+
+                let best = NEIGHBORS.iter()
+                    .filter(|offset| grid.can_step(cell, **offset))
+                    .filter_map(|offset| grid.index(cell + *offset).map(|next| (*offset, costs[next])))
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+
+                if let Some((offset, _)) = best.filter(|(_, cost)| *cost < costs[index]) {
+                    directions[index] = offset.as_vec2().normalize();
+                }
+            }
+        }
+
+        FlowField { goal, costs, directions }
+    }
+
+    pub fn cost(&self, grid : &FlowFieldGrid, cell : IVec2) -> f32 {
+        grid.index(cell).map(|index| self.costs[index]).unwrap_or(f32::INFINITY)
+    }
+
+    /// The direction to move in from a position, or `None` if the goal can't be reached from there.
+    /// Standing in the goal cell returns a zero direction.
+    pub fn sample(&self, grid : &FlowFieldGrid, position : Vec2) -> Option<Vec2> {
+        let cell = grid.cell(position);
+        if let Some(index) = grid.index(cell).filter(|index| self.costs[*index].is_finite()) {
+            return Some(self.directions[index]);
+        }
+
+        // Agents pushed slightly off the navmesh steer back towards the cheapest cell next to them.
+        NEIGHBORS.iter()
+            .map(|offset| cell + *offset)
+            .filter(|next| self.cost(grid, *next).is_finite())
+            .min_by(|a, b| self.cost(grid, *a).total_cmp(&self.cost(grid, *b)))
+            .map(|next| (grid.cell_center(next) - position).normalize_or_zero())
+    }
+}
+
+//==============================================================================================
+//        FlowFields Resource
+//==============================================================================================
+
+#[derive(Resource, Default)]
+pub struct FlowFields {
+    fields : HashMap<Entity, FlowField>,
+}
+
+impl FlowFields {
+    pub fn get(&self, target : Entity) -> Option<&FlowField> {
+        self.fields.get(&target)
+    }
+
+    /// Samples the flow field of a target at a position.
+    pub fn direction(&self, grid : &FlowFieldGrid, target : Entity, position : Vec2) -> Option<Vec2> {
+        self.fields.get(&target)?.sample(grid, position)
+    }
+}
+
+//==============================================================================================
+//        FlowField Systems
+//==============================================================================================
+
+pub fn rebuild_flow_field_grid(
+    mut rebuilt : EventReader<NavMeshRebuilt>,
+    mut grid : ResMut<FlowFieldGrid>,
+    mut fields : ResMut<FlowFields>,
+    navmesh : NavmeshQuery,
+) {
+    if rebuilt.is_empty() { return }
+    rebuilt.clear();
+
+    grid.rebuild(&navmesh);
+    fields.fields.clear();
+}
+
+/// Recomputes the field of every target that moved into a different cell, and drops the fields
+/// of targets that are gone.
+pub fn update_flow_fields(
+    grid : Res<FlowFieldGrid>,
+    mut fields : ResMut<FlowFields>,
+    targets : Query<(Entity, &Transform), With<FlowFieldTarget>>,
+) {
+    if !grid.is_built() { return }
+
+    fields.fields.retain(|entity, _| targets.contains(*entity));
+
+    for (entity, transform) in targets.iter() {
+        let goal = grid.cell(transform.translation.xz());
+        if fields.fields.get(&entity).is_some_and(|field| field.goal == goal) { continue; }
+        fields.fields.insert(entity, FlowField::new(&grid, goal));
+    }
+}
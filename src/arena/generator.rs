@@ -0,0 +1,300 @@
+use std::f32::consts::TAU;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{arena::{beacon::Beacon, ArenaProp, NavMeshRebuilt, NavmeshQuery, Obstacle, SpawnZone}, util::obstacle_layer, GameState};
+
+const BASE_PIECES: f32 = 24.0;
+const MAX_DENSITY: f32 = 3.0;
+const PLACEMENT_ATTEMPTS: usize = 20;
+/// Keeps pieces off the outer edge so enemies can always walk around the arena.
+const EDGE_MARGIN: f32 = 3.0;
+/// The gap left between two pieces, wide enough for enemies to fit through.
+const PIECE_SPACING: f32 = 1.5;
+/// How far from the beacon the reachability check aims, the beacon itself is a hole in the navmesh.
+const BEACON_APPROACH_DISTANCE: f32 = 2.5;
+const MAX_REROLLS: u32 = 5;
+
+//==============================================================================================
+//        Arena Generator Plugin
+//==============================================================================================
+
+pub struct ArenaGeneratorPlugin;
+
+impl Plugin for ArenaGeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ArenaSettings>()
+            .init_resource::<ArenaLayout>()
+            .add_systems(Update, verify_arena_layout.run_if(in_state(GameState::InGame)))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Arena Settings
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArenaTheme {
+    /// Broken pillars and crumbling walls.
+    Ruins,
+    /// Mostly boulders.
+    #[default]
+    Wilds,
+    /// Long walls that split the arena into lanes.
+    Fortress,
+}
+
+impl ArenaTheme {
+    pub const ALL : [ArenaTheme; 3] = [ArenaTheme::Ruins, ArenaTheme::Wilds, ArenaTheme::Fortress];
+
+    fn weights(&self) -> [(PieceKind, u32); 4] {
+        match self {
+            ArenaTheme::Ruins => [(PieceKind::Rock, 1), (PieceKind::Pillar, 4), (PieceKind::Ruin, 4), (PieceKind::Wall, 2)],
+            ArenaTheme::Wilds => [(PieceKind::Rock, 8), (PieceKind::Pillar, 1), (PieceKind::Ruin, 1), (PieceKind::Wall, 0)],
+            ArenaTheme::Fortress => [(PieceKind::Rock, 0), (PieceKind::Pillar, 3), (PieceKind::Ruin, 1), (PieceKind::Wall, 5)],
+        }
+    }
+
+    pub fn ground_color(&self) -> Color {
+        match self {
+            ArenaTheme::Ruins => Color::srgb(0.45, 0.42, 0.32),
+            ArenaTheme::Wilds => Color::srgb(0.3, 0.5, 0.3),
+            ArenaTheme::Fortress => Color::srgb(0.35, 0.35, 0.38),
+        }
+    }
+
+    fn piece_color(&self) -> Color {
+        match self {
+            ArenaTheme::Ruins => Color::srgb(0.7, 0.65, 0.55),
+            ArenaTheme::Wilds => Color::srgb(0.45, 0.43, 0.4),
+            ArenaTheme::Fortress => Color::srgb(0.55, 0.55, 0.6),
+        }
+    }
+}
+
+/// How the next arena is generated. The same seed, theme and density always give the same layout.
+#[derive(Resource, Clone, Debug)]
+pub struct ArenaSettings {
+    /// A new seed is rolled for every run when there is none.
+    pub seed : Option<u64>,
+    /// Picked from the seed when there is none.
+    pub theme : Option<ArenaTheme>,
+    /// Scales how many pieces are placed, 1 is the normal amount and 0 leaves the arena empty.
+    pub density : f32,
+}
+
+impl Default for ArenaSettings {
+    fn default() -> Self {
+        ArenaSettings {
+            seed : None,
+            theme : None,
+            density : 1.0,
+        }
+    }
+}
+
+impl ArenaSettings {
+    pub fn roll_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+
+    pub fn theme(&self, seed : u64) -> ArenaTheme {
+        self.theme.unwrap_or(ArenaTheme::ALL[(seed % ArenaTheme::ALL.len() as u64) as usize])
+    }
+}
+
+//==============================================================================================
+//        Arena Layout
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceKind {
+    Rock,
+    Pillar,
+    Ruin,
+    Wall,
+}
+
+/// One generated obstacle, `size` is its full extents.
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaPiece {
+    pub kind : PieceKind,
+    pub position : Vec2,
+    pub rotation : f32,
+    pub size : Vec3,
+}
+
+impl ArenaPiece {
+    fn random(kind : PieceKind, position : Vec2, rng : &mut impl Rng) -> Self {
+        let size = match kind {
+            PieceKind::Rock => Vec3::splat(rng.random_range(1.6..3.2)),
+            PieceKind::Pillar => {
+                let width = rng.random_range(1.0..1.6);
+                Vec3::new(width, rng.random_range(3.0..5.0), width)
+            }
+            PieceKind::Ruin => Vec3::new(rng.random_range(1.5..3.0), rng.random_range(1.0..1.5), rng.random_range(1.0..2.0)),
+            PieceKind::Wall => Vec3::new(rng.random_range(4.0..8.0), 2.5, 0.6),
+        };
+        ArenaPiece { kind, position, rotation: rng.random_range(0.0..TAU), size }
+    }
+
+    /// The radius of a circle around the piece's footprint, used to keep pieces apart.
+    pub fn radius(&self) -> f32 {
+        self.size.xz().length() * 0.5
+    }
+}
+
+/// The pieces of the current arena and the areas they have to stay out of.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ArenaLayout {
+    pub seed : u64,
+    pub theme : ArenaTheme,
+    /// Half the size of the arena the pieces are scattered over.
+    pub half_size : Vec2,
+    pub pieces : Vec<ArenaPiece>,
+    /// Circles that are kept clear, around the beacon, spawn zones, portals and the like.
+    pub reserved : Vec<(Vec2, f32)>,
+    /// How often the layout was thrown away because the beacon could not be reached.
+    rerolls : u32,
+    verified : bool,
+}
+
+impl ArenaLayout {
+    /// Scatters pieces over the arena, skipping spots that overlap a reserved area or another piece.
+    pub fn generate(settings : &ArenaSettings, seed : u64, half_size : Vec2, reserved : Vec<(Vec2, f32)>) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let weights = settings.theme(seed).weights();
+        let total_weight = weights.iter().map(|(_, weight)| weight).sum::<u32>().max(1);
+        let count = (BASE_PIECES * settings.density.clamp(0.0, MAX_DENSITY)).round() as usize;
+        let extent = (half_size - EDGE_MARGIN).max(Vec2::ZERO);
+
+        let mut pieces : Vec<ArenaPiece> = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut roll = rng.random_range(0..total_weight);
+            let kind = weights.iter()
+                .find(|(_, weight)| {
+                    if roll < *weight { return true }
+                    roll -= weight;
+                    false
+                })
+                .map(|(kind, _)| *kind)
+                .unwrap_or(PieceKind::Rock);
+
+            let piece = (0..PLACEMENT_ATTEMPTS)
+                .map(|_| ArenaPiece::random(kind, Vec2::new(rng.random_range(-extent.x..=extent.x), rng.random_range(-extent.y..=extent.y)), &mut rng))
+                .find(|piece| {
+                    reserved.iter().all(|(center, radius)| piece.position.distance(*center) >= radius + piece.radius())
+                        && pieces.iter().all(|other| piece.position.distance(other.position) >= piece.radius() + other.radius() + PIECE_SPACING)
+                });
+            if let Some(piece) = piece {
+                pieces.push(piece);
+            }
+        }
+
+        ArenaLayout { seed, theme: settings.theme(seed), half_size, pieces, reserved, rerolls: 0, verified: false }
+    }
+}
+
+/// Marks the obstacles spawned from the [`ArenaLayout`], so they can be swapped out for a new layout.
+#[derive(Component)]
+pub struct GeneratedPiece;
+
+pub fn spawn_arena_layout(
+    commands : &mut Commands,
+    meshes : &mut Assets<Mesh>,
+    materials : &mut Assets<StandardMaterial>,
+    layout : &ArenaLayout,
+) {
+    let material = materials.add(layout.theme.piece_color());
+    for piece in layout.pieces.iter() {
+        let (mesh, collider, height) = match piece.kind {
+            PieceKind::Rock => {
+                let radius = piece.size.x * 0.5;
+                (meshes.add(Sphere::new(radius)), Collider::sphere(radius), radius * 0.5)
+            }
+            PieceKind::Pillar => {
+                let radius = piece.size.x * 0.5;
+                (meshes.add(Cylinder::new(radius, piece.size.y)), Collider::cylinder(radius, piece.size.y), piece.size.y * 0.5)
+            }
+            PieceKind::Ruin | PieceKind::Wall => (
+                meshes.add(Cuboid::from_size(piece.size)),
+                Collider::cuboid(piece.size.x, piece.size.y, piece.size.z),
+                piece.size.y * 0.5,
+            ),
+        };
+
+        commands.spawn((
+            Name::new(format!("{:?}", piece.kind)),
+            Mesh3d(mesh),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(piece.position.x, height, piece.position.y).with_rotation(Quat::from_rotation_y(piece.rotation)),
+            collider,
+            RigidBody::Static,
+            obstacle_layer(),
+            Obstacle,
+            ArenaProp,
+            GeneratedPiece,
+        ));
+    }
+}
+
+//==============================================================================================
+//        Layout Verification
+//==============================================================================================
+
+/// Once the navmesh is built around a new layout, every spawn zone has to have a path to the
+/// beacon. A layout that cuts one off is rerolled with the next seed and the same theme, and after
+/// too many tries the arena is left empty so the run stays fair.
+pub fn verify_arena_layout(
+    mut commands : Commands,
+    mut rebuilt : EventReader<NavMeshRebuilt>,
+    mut layout : ResMut<ArenaLayout>,
+    settings : Res<ArenaSettings>,
+    mut meshes : ResMut<Assets<Mesh>>,
+    mut materials : ResMut<Assets<StandardMaterial>>,
+    pieces : Query<Entity, With<GeneratedPiece>>,
+    zones : Query<&Transform, With<SpawnZone>>,
+    beacons : Query<&Transform, With<Beacon>>,
+    navmesh : NavmeshQuery,
+) {
+    // An empty layout can't cut anything off, whatever is unreachable was placed by hand.
+    if rebuilt.read().count() == 0 || layout.verified || layout.pieces.is_empty() { return }
+    let Some(mesh) = navmesh.navmesh() else { return };
+
+    let reachable = beacons.iter().all(|beacon| {
+        let beacon = beacon.translation.xz();
+        zones.iter().all(|zone| {
+            let zone = zone.translation.xz();
+            let approach = beacon + (zone - beacon).normalize_or(Vec2::X) * BEACON_APPROACH_DISTANCE;
+            mesh.path(zone, approach).is_some()
+        })
+    });
+    if reachable {
+        layout.verified = true;
+        return;
+    }
+
+    for piece in pieces.iter() {
+        commands.entity(piece).despawn();
+    }
+
+    let rerolls = layout.rerolls + 1;
+    if rerolls > MAX_REROLLS {
+        warn!("No arena layout with a reachable beacon after {MAX_REROLLS} rerolls, leaving the arena empty");
+        layout.pieces.clear();
+        layout.verified = true;
+        return;
+    }
+
+    let reroll = ArenaSettings {
+        theme : Some(layout.theme),
+        ..settings.clone()
+    };
+    let reserved = std::mem::take(&mut layout.reserved);
+    *layout = ArenaLayout { rerolls, ..ArenaLayout::generate(&reroll, layout.seed.wrapping_add(1), layout.half_size, reserved) };
+    spawn_arena_layout(&mut commands, &mut meshes, &mut materials, &layout);
+}
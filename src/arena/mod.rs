@@ -2,12 +2,22 @@ use std::f32::consts::FRAC_PI_2;
 use bevy::{color::palettes, ecs::system::SystemParam, prelude::*};
 use avian3d::prelude::*;
 use vleue_navigator::{prelude::{ManagedNavMesh, NavMeshSettings, NavMeshStatus, NavMeshUpdateMode}, NavMesh, NavMeshDebug, Triangulation};
-use crate::{arena::beacon::{spawn_beacon, BeaconPlugin}, GameState};
+use crate::{arena::{beacon::{spawn_beacon, BeaconPlugin}, definition::{ArenaDefinitionPlugin, CurrentArena, ObstacleDefinition, PortalDefinition, SpawnZoneDefinition}, flow_field::{FlowFieldGrid, FlowFieldPlugin}, generator::{spawn_arena_layout, ArenaGeneratorPlugin, ArenaLayout, ArenaSettings}, portal::{Portal, PortalPlugin}}, assets::BeaconAssets, util::{obstacle_layer, GameInit}, GameState};
 use vleue_navigator::{prelude::*, Path};
 
 pub mod beacon;
+pub mod definition;
+pub mod editor;
+pub mod flow_field;
+pub mod generator;
+pub mod portal;
 
-pub const ARENA_SIZE: f32 = 50.0;
+const PATH_SAMPLE_SPACING: f32 = 0.5;
+/// Generated obstacles keep this far from the beacons and the player start.
+const BEACON_CLEARANCE: f32 = 6.0;
+/// Generated obstacles keep this far from waypoints and the edges of spawn zones and portals.
+const LANDMARK_CLEARANCE: f32 = 2.0;
+pub const NAVMESH_DEBUG_COLOR: Srgba = palettes::tailwind::RED_800;
 
 //==============================================================================================
 //        ArenaPlugin
@@ -18,8 +28,17 @@ pub struct ArenaPlugin;
 impl Plugin for ArenaPlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_plugins(ArenaDefinitionPlugin)
             .add_plugins(BeaconPlugin)
-            .add_systems(OnEnter(GameState::InGame), build_arena);
+            .add_plugins(FlowFieldPlugin)
+            .add_plugins(PortalPlugin)
+            .add_plugins(ArenaGeneratorPlugin)
+            
+            .add_event::<NavMeshRebuilt>()
+            
+            .add_systems(OnEnter(GameState::InGame), build_arena.in_set(GameInit))
+            .add_systems(PreUpdate, detect_navmesh_rebuild)
+        ;
     }
 }
 
@@ -33,10 +52,45 @@ pub struct ArenaProp;
 #[derive(Component)]
 pub struct Ground;
 
+/// A named area enemies can be spawned into, see [`SpawnEnemiesEventBuilder::in_zone`](crate::enemy::SpawnEnemiesEventBuilder::in_zone).
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct SpawnZone {
+    pub name : String,
+    pub radius : f32,
+}
+
+impl SpawnZone {
+    pub fn new(name : impl Into<String>, radius : f32) -> Self {
+        SpawnZone { name: name.into(), radius }
+    }
+}
+
+/// One stop on a named patrol route, see [`Orders::Patrol`](crate::enemy::orders::Orders::Patrol).
+/// Patrols walk the waypoints of their route by `order` and loop back around to the first one.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct PatrolWaypoint {
+    pub route : String,
+    pub order : u32,
+}
+
+impl PatrolWaypoint {
+    pub fn new(route : impl Into<String>, order : u32) -> Self {
+        PatrolWaypoint { route: route.into(), order }
+    }
+}
+
 pub fn build_arena(
     mut commands : Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut graphs : ResMut<Assets<AnimationGraph>>,
+    mut layout : ResMut<ArenaLayout>,
+    settings : Res<ArenaSettings>,
+    arena : Res<CurrentArena>,
+    beacon_assets : Res<BeaconAssets>,
+    asset_server : Res<AssetServer>,
     previous_setup: Query<Entity, With<ArenaProp>>
 ) {
     
@@ -44,28 +98,87 @@ pub fn build_arena(
         commands.entity(entity).despawn();
     }
     
-    let half_size = ARENA_SIZE / 2.0;
+    let half_size = arena.half_size();
+    let seed = settings.roll_seed();
+    let theme = settings.theme(seed);
+    let mut reserved = vec![(arena.player_start, BEACON_CLEARANCE)];
+    
+    commands.insert_resource(FlowFieldGrid::new(arena.size));
+    commands.insert_resource(AmbientLight {
+        brightness : arena.lighting.ambient_brightness,
+        ..default()
+    });
     
     // Spawn the ground
     commands.spawn((
         Name::new("Ground"),
-        Mesh3d(meshes.add(Plane3d::default().mesh().size(ARENA_SIZE, ARENA_SIZE))),
-        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.5, 0.3))),
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(arena.size.x, arena.size.y))),
+        MeshMaterial3d(materials.add(arena.ground.material(theme))),
         ArenaProp,
         RigidBody::Static,
         Collider::half_space(Vec3::Y),
         Ground,
     ));
     
+    let (r, g, b) = arena.lighting.sun_color;
+    let rotation = arena.lighting.sun_rotation;
+    commands.spawn((
+        Name::new("Sun"),
+        DirectionalLight {
+            color : Color::srgb(r, g, b),
+            illuminance : arena.lighting.sun_illuminance,
+            shadows_enabled : true,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z)),
+        ArenaProp,
+    ));
+    
+    for beacon in arena.beacons.iter() {
+        spawn_beacon(&mut commands, &mut graphs, &beacon_assets, beacon);
+        reserved.push((beacon.position, BEACON_CLEARANCE));
+    }
+    
+    for zone in arena.spawn_zones.iter() {
+        spawn_spawn_zone(&mut commands, zone);
+        reserved.push((zone.position, zone.radius + LANDMARK_CLEARANCE));
+    }
+    
+    for route in arena.patrol_routes.iter() {
+        for (waypoint, position) in route.waypoints() {
+            spawn_patrol_waypoint(&mut commands, waypoint, position);
+            reserved.push((position, LANDMARK_CLEARANCE));
+        }
+    }
+    
+    // Definitions don't change how far out a portal spawns its enemies.
+    let portal_spawn_radius = Portal::default().spawn_radius;
+    for definition in arena.portals.iter() {
+        spawn_portal(&mut commands, definition);
+        reserved.push((definition.position, portal_spawn_radius + LANDMARK_CLEARANCE));
+    }
+    
+    for obstacle in arena.obstacles.iter() {
+        spawn_obstacle(&mut commands, &mut meshes, &mut materials, &asset_server, obstacle);
+        reserved.push((obstacle.position.xz(), obstacle.shape.radius() + LANDMARK_CLEARANCE));
+    }
+    
+    if arena.procedural {
+        *layout = ArenaLayout::generate(&settings, seed, half_size, reserved);
+        spawn_arena_layout(&mut commands, &mut meshes, &mut materials, &layout);
+    } else {
+        *layout = ArenaLayout::default();
+    }
+    
     commands.spawn((
         Name::new("Nav Mesh"),
         NavMeshSettings{
             // Define the outer borders of the navmesh.
             fixed: Triangulation::from_outer_edges(&[
-                vec2(-half_size, -half_size),
-                vec2(half_size, -half_size),
-                vec2(half_size, half_size),
-                vec2(-half_size, half_size),
+                vec2(-half_size.x, -half_size.y),
+                vec2(half_size.x, -half_size.y),
+                vec2(half_size.x, half_size.y),
+                vec2(-half_size.x, half_size.y),
             ]),
             build_timeout: Some(1.0),
             simplify: 0.005,
@@ -73,10 +186,97 @@ pub fn build_arena(
             agent_radius : 0.6,
             ..default()
         },
-        NavMeshDebug(palettes::tailwind::RED_800.into()),
+        NavMeshDebug(NAVMESH_DEBUG_COLOR.into()),
         NavMeshUpdateMode::Direct,
         Transform::from_xyz(0.0, 0.1, 0.0).with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+        ArenaProp,
+    ));
+}
+
+//==============================================================================================
+//        Arena Pieces
+//==============================================================================================
+
+/// A hand placed obstacle, keeps the definition it was spawned from so the arena can be saved again.
+#[derive(Component, Clone, Debug)]
+pub struct PlacedObstacle(pub ObstacleDefinition);
+
+pub fn spawn_spawn_zone(commands : &mut Commands, zone : &SpawnZoneDefinition) -> Entity {
+    commands.spawn((
+        Name::new(format!("Spawn Zone {}", zone.name)),
+        SpawnZone::new(zone.name.clone(), zone.radius),
+        Transform::from_xyz(zone.position.x, 0.0, zone.position.y),
+        ArenaProp,
+    )).id()
+}
+
+pub fn spawn_patrol_waypoint(commands : &mut Commands, waypoint : PatrolWaypoint, position : Vec2) -> Entity {
+    commands.spawn((
+        Name::new(format!("Patrol Waypoint {} {}", waypoint.route, waypoint.order)),
+        waypoint,
+        Transform::from_xyz(position.x, 0.0, position.y),
+        ArenaProp,
+    )).id()
+}
+
+/// The collider, health and visuals of the portal are added by [`build_portal`](portal::build_portal).
+pub fn spawn_portal(commands : &mut Commands, definition : &PortalDefinition) -> Entity {
+    let mut portal = Portal::default();
+    if let Some(enemies_per_spawn) = definition.enemies_per_spawn { portal = portal.with_enemies_per_spawn(enemies_per_spawn) }
+    if let Some(interval) = definition.interval { portal = portal.with_interval(interval) }
+    commands.spawn((
+        Name::new("Portal"),
+        portal,
+        Transform::from_xyz(definition.position.x, 0.0, definition.position.y),
+    )).id()
+}
+
+pub fn spawn_obstacle(
+    commands : &mut Commands,
+    meshes : &mut Assets<Mesh>,
+    materials : &mut Assets<StandardMaterial>,
+    asset_server : &AssetServer,
+    obstacle : &ObstacleDefinition,
+) -> Entity {
+    let transform = Transform::from_translation(obstacle.position).with_rotation(Quat::from_rotation_y(obstacle.rotation.to_radians()));
+    let mut entity = commands.spawn((
+        Name::new("Obstacle"),
+        transform,
+        obstacle.shape.collider(),
+        RigidBody::Static,
+        obstacle_layer(),
+        Obstacle,
+        ArenaProp,
+        PlacedObstacle(obstacle.clone()),
     ));
+    match &obstacle.model {
+        Some(model) => { entity.insert(SceneRoot(asset_server.load(model.clone()))); }
+        None => {
+            let (r, g, b) = obstacle.color.unwrap_or((0.5, 0.5, 0.5));
+            entity.insert((Mesh3d(meshes.add(obstacle.shape.mesh())), MeshMaterial3d(materials.add(Color::srgb(r, g, b)))));
+        }
+    }
+    entity.id()
+}
+
+//==============================================================================================
+//        Navmesh Rebuilds
+//==============================================================================================
+
+/// Sent every time the navmesh finishes building. Anything that caches data derived from the
+/// navmesh (paths, flow fields) should listen for this and throw that data away.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct NavMeshRebuilt;
+
+pub fn detect_navmesh_rebuild(
+    navmeshes : Query<Ref<NavMeshStatus>, With<ManagedNavMesh>>,
+    mut rebuilt : EventWriter<NavMeshRebuilt>,
+) {
+    for status in navmeshes.iter() {
+        if status.is_changed() && *status == NavMeshStatus::Built {
+            rebuilt.write(NavMeshRebuilt);
+        }
+    }
 }
 
 //==============================================================================================
@@ -99,4 +299,29 @@ impl<'w> NavmeshQuery<'w> {
         let Some(navmesh) = self.navmeshes.get(self.navmesh.0.id()) else { return None};
         navmesh.path(from.translation.xz(), to)
     }
+    
+    /// The navmesh, as long as it is built.
+    pub fn navmesh(&self) -> Option<&NavMesh> {
+        if *self.navmesh.1 != NavMeshStatus::Built { return None};
+        self.navmeshes.get(self.navmesh.0.id())
+    }
+    
+    /// Checks that the straight lines between the points are still on the navmesh. The points
+    /// themselves are skipped since path corners sit right on the edge of the navmesh.
+    pub fn is_path_clear(&self, points : &[Vec2]) -> bool {
+        let Some(navmesh) = self.navmeshes.get(self.navmesh.0.id()) else { return false };
+        points.windows(2).all(|segment| {
+            let samples = (segment[0].distance(segment[1]) / PATH_SAMPLE_SPACING).ceil().max(1.0) as usize;
+            (1..samples).all(|sample| navmesh.is_in_mesh(segment[0].lerp(segment[1], sample as f32 / samples as f32)))
+        })
+    }
+    
+    pub fn is_built(&self) -> bool {
+        *self.navmesh.1 == NavMeshStatus::Built
+    }
+    
+    pub fn is_walkable(&self, point : Vec2) -> bool {
+        let Some(navmesh) = self.navmeshes.get(self.navmesh.0.id()) else { return false };
+        navmesh.is_in_mesh(point)
+    }
 }
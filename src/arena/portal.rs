@@ -0,0 +1,256 @@
+use std::f32::consts::FRAC_PI_2;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::{definition::CurrentArena, ArenaProp, Obstacle}, enemy::{EnemyType, SpawnEnemiesEventBuilder}, util::{GameCollisionLayer, Health}, GameState, RunOutcome};
+
+const PORTAL_RADIUS: f32 = 1.2;
+const PORTAL_HEIGHT: f32 = 3.0;
+const PORTAL_HEALTH: f32 = 60.0;
+const PORTAL_INTERVAL: f32 = 15.0;
+const PORTAL_TELEGRAPH: f32 = 2.0;
+const PORTAL_ENEMIES_PER_SPAWN: u32 = 3;
+/// Enemies are placed around the portal, outside of the hole it cuts into the navmesh.
+const PORTAL_SPAWN_RADIUS: f32 = 3.5;
+
+//==============================================================================================
+//        Portal Plugin
+//==============================================================================================
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PortalAssets>()
+            .add_event::<PortalEvent>()
+            .add_observer(build_portal)
+            .add_systems(Update, (charge_portals, open_portals, close_destroyed_portals, complete_portal_objective).chain().run_if(in_state(GameState::InGame)))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Portal Components
+//==============================================================================================
+
+/// Whether closing the portals wins the run, set per arena in its [`ArenaDefinition`](super::definition::ArenaDefinition).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WinCondition {
+    /// The portals keep the pressure on, the run only ends when it is lost.
+    #[default]
+    Endless,
+    /// The run is won once every portal in the arena is closed.
+    AllPortalsClosed,
+}
+
+/// A rift in the arena that keeps spawning enemies until it is destroyed. Spawning one only needs
+/// this and a transform, the collider, health and visuals are added when it shows up.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct Portal {
+    /// Portals without any weights spawn minions.
+    pub weights : Vec<(EnemyType, u32)>,
+    pub enemies_per_spawn : u32,
+    pub spawn_radius : f32,
+    /// How long the portal stays shut between spawns.
+    pub interval : Timer,
+    /// How long the telegraph is shown before the enemies come through.
+    pub telegraph : f32,
+}
+
+impl Default for Portal {
+    fn default() -> Self {
+        Portal {
+            weights : Vec::new(),
+            enemies_per_spawn : PORTAL_ENEMIES_PER_SPAWN,
+            spawn_radius : PORTAL_SPAWN_RADIUS,
+            interval : Timer::from_seconds(PORTAL_INTERVAL, TimerMode::Once),
+            telegraph : PORTAL_TELEGRAPH,
+        }
+    }
+}
+
+impl Portal {
+    pub fn with_weight(mut self, enemy_type : EnemyType, weight : u32) -> Self {
+        self.weights.retain(|(other, _)| *other != enemy_type);
+        self.weights.push((enemy_type, weight));
+        self
+    }
+
+    pub fn with_enemies_per_spawn(mut self, enemies_per_spawn : u32) -> Self {
+        self.enemies_per_spawn = enemies_per_spawn;
+        self
+    }
+
+    pub fn with_interval(mut self, interval : f32) -> Self {
+        self.interval = Timer::from_seconds(interval, TimerMode::Once);
+        self
+    }
+}
+
+/// A portal about to spawn, the telegraph is shown until the timer runs out.
+#[derive(Component)]
+pub struct PortalCharging {
+    timer : Timer,
+    telegraph : Entity,
+}
+
+//==============================================================================================
+//        Portal Events
+//==============================================================================================
+
+/// The "close the portals" objective. `AllClosed` is sent once the last portal is destroyed, which
+/// wins the run.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum PortalEvent {
+    Charging { portal : Entity },
+    Opened { portal : Entity },
+    Closed { portal : Entity },
+    AllClosed,
+}
+
+#[derive(Resource)]
+pub struct PortalAssets {
+    ring_mesh : Handle<Mesh>,
+    ring_material : Handle<StandardMaterial>,
+    telegraph_mesh : Handle<Mesh>,
+    telegraph_material : Handle<StandardMaterial>,
+}
+
+impl FromWorld for PortalAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let ring_mesh = meshes.add(Torus::new(PORTAL_RADIUS * 0.7, PORTAL_RADIUS));
+        let telegraph_mesh = meshes.add(Cylinder::new(1.0, 0.02));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let color = Color::srgb(0.6, 0.2, 1.0);
+        PortalAssets {
+            ring_mesh,
+            ring_material : materials.add(StandardMaterial {
+                base_color : color,
+                emissive : color.to_linear() * 3.0,
+                ..default()
+            }),
+            telegraph_mesh,
+            telegraph_material : materials.add(StandardMaterial {
+                base_color : Color::srgba(0.6, 0.2, 1.0, 0.35),
+                alpha_mode : AlphaMode::Blend,
+                unlit : true,
+                ..default()
+            }),
+        }
+    }
+}
+
+//==============================================================================================
+//        Portal Systems
+//==============================================================================================
+
+/// Portals block navigation like any other [`Obstacle`] and sit on the enemy layer as well, so
+/// the player's spells can hit them.
+pub fn build_portal(
+    trigger : Trigger<OnAdd, Portal>,
+    mut commands : Commands,
+    assets : Res<PortalAssets>,
+) {
+    commands.entity(trigger.target())
+        .insert((
+            ArenaProp,
+            Obstacle,
+            Health::new(PORTAL_HEALTH),
+            RigidBody::Static,
+            Collider::cylinder(PORTAL_RADIUS, PORTAL_HEIGHT),
+            CollisionLayers::new([GameCollisionLayer::Obstacle, GameCollisionLayer::Enemy], LayerMask::ALL),
+            Visibility::default(),
+        ))
+        .with_child((
+            Mesh3d(assets.ring_mesh.clone()),
+            MeshMaterial3d(assets.ring_material.clone()),
+            Transform::from_xyz(0.0, PORTAL_HEIGHT / 2.0, 0.0).with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+        ));
+}
+
+pub fn charge_portals(
+    mut commands : Commands,
+    mut portals : Query<(Entity, &mut Portal), Without<PortalCharging>>,
+    assets : Res<PortalAssets>,
+    mut portal_events : EventWriter<PortalEvent>,
+    time : Res<Time>,
+) {
+    for (entity, mut portal) in portals.iter_mut() {
+        portal.interval.tick(time.delta());
+        if !portal.interval.finished() { continue }
+
+        let telegraph = commands.spawn((
+            Name::new("Portal Telegraph"),
+            Mesh3d(assets.telegraph_mesh.clone()),
+            MeshMaterial3d(assets.telegraph_material.clone()),
+            Transform::from_xyz(0.0, 0.05, 0.0).with_scale(Vec3::new(portal.spawn_radius, 1.0, portal.spawn_radius)),
+            ChildOf(entity),
+        )).id();
+        commands.entity(entity).insert(PortalCharging {
+            timer : Timer::from_seconds(portal.telegraph, TimerMode::Once),
+            telegraph,
+        });
+        portal_events.write(PortalEvent::Charging { portal: entity });
+    }
+}
+
+pub fn open_portals(
+    mut commands : Commands,
+    mut portals : Query<(Entity, &Transform, &mut Portal, &mut PortalCharging)>,
+    mut portal_events : EventWriter<PortalEvent>,
+    time : Res<Time>,
+) {
+    for (entity, transform, mut portal, mut charging) in portals.iter_mut() {
+        charging.timer.tick(time.delta());
+        if !charging.timer.finished() { continue }
+
+        let weights = if portal.weights.is_empty() { vec![(EnemyType::Minion, 1)] } else { portal.weights.clone() };
+        let spawn = weights.into_iter().fold(
+            SpawnEnemiesEventBuilder::new(transform.translation)
+                .with_number_of_enemies(portal.enemies_per_spawn)
+                .with_radius(portal.spawn_radius),
+            |spawn, (enemy_type, weight)| spawn.with_weight(enemy_type, weight),
+        );
+        commands.trigger(spawn.build());
+        commands.entity(charging.telegraph).despawn();
+        commands.entity(entity).remove::<PortalCharging>();
+        portal.interval.reset();
+        portal_events.write(PortalEvent::Opened { portal: entity });
+    }
+}
+
+/// A destroyed portal stops spawning right away, along with anything it had charging.
+pub fn close_destroyed_portals(
+    mut commands : Commands,
+    portals : Query<(Entity, &Health), With<Portal>>,
+    mut portal_events : EventWriter<PortalEvent>,
+) {
+    let mut remaining = portals.iter().count();
+    for (entity, health) in portals.iter() {
+        if health.current_health > 0.0 { continue }
+        commands.entity(entity).despawn();
+        portal_events.write(PortalEvent::Closed { portal: entity });
+        remaining -= 1;
+        if remaining == 0 {
+            portal_events.write(PortalEvent::AllClosed);
+        }
+    }
+}
+
+pub fn complete_portal_objective(
+    mut commands : Commands,
+    mut portal_events : EventReader<PortalEvent>,
+    arena : Res<CurrentArena>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    if !portal_events.read().any(|event| matches!(event, PortalEvent::AllClosed)) { return }
+    if arena.win_condition != WinCondition::AllPortalsClosed { return }
+    info!("Every portal is closed");
+    commands.insert_resource(RunOutcome::Won);
+    next_state.set(GameState::GameOver);
+}
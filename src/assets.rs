@@ -15,7 +15,7 @@ impl Plugin for AssetLoadingPlugin {
     fn build(&self, app: &mut App) {
         app.add_loading_state(
             LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::InGame)
+                .continue_to_state(GameState::LoadingArena)
                 .load_collection::<WizardAssets>()
                 .load_collection::<SpellAssets>()
                 .load_collection::<EnemyAssets>()
@@ -77,6 +77,8 @@ pub struct EnemyAssets {
     pub skeleton_minion_spawn: Handle<AnimationClip>,
     #[asset(path = "models/enemy/Skeleton_Minion.glb#Animation39")]
     pub skeleton_minion_hit: Handle<AnimationClip>,
+    #[asset(path = "models/enemy/Skeleton_Minion.glb#Animation23")]
+    pub skeleton_minion_death: Handle<AnimationClip>,
     #[asset(path = "models/enemy/Skeleton_Minion.glb#Animation3")]
     pub skeleton_minion_stab: Handle<AnimationClip>,
     #[asset(path = "models/enemy/Skeleton_Minion.glb#Animation80")]
@@ -100,6 +102,8 @@ pub struct EnemyAnimationGraphs {
     pub minion_spawn: AnimationNodeIndex,
     pub minion_stab: AnimationNodeIndex,
     pub minion_spellcast: AnimationNodeIndex,
+    pub minion_hit: AnimationNodeIndex,
+    pub minion_death: AnimationNodeIndex,
 }
 
 const MINION_UPPER: [&str; 4] = [
@@ -130,6 +134,8 @@ impl FromWorld for EnemyAnimationGraphs {
         let minion_spawn = graph.add_clip(assets.skeleton_minion_spawn.clone(), 1.0, graph.root);
         let minion_stab = graph.add_clip_with_mask(assets.skeleton_minion_stab.clone(), 0b10, 1.0, graph.root);
         let minion_spellcast = graph.add_clip(assets.skeleton_minion_spell_casting.clone(), 1.0, graph.root);
+        let minion_hit = graph.add_clip_with_mask(assets.skeleton_minion_hit.clone(), 0b10, 1.0, graph.root);
+        let minion_death = graph.add_clip(assets.skeleton_minion_death.clone(), 1.0, graph.root);
     
         EnemyAnimationGraphs {
             minion_graph: world.resource_mut::<Assets<AnimationGraph>>().add(graph),
@@ -139,7 +145,9 @@ impl FromWorld for EnemyAnimationGraphs {
             minion_run_fast,
             minion_spawn,
             minion_stab,
-            minion_spellcast
+            minion_spellcast,
+            minion_hit,
+            minion_death,
         }
     }
 }
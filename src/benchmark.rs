@@ -0,0 +1,102 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{enemy::{EnemyCount, EnemyType, SpawnEnemiesEventBuilder}, util::PostGameInit, GameState};
+
+const BENCHMARK_ENEMIES: u32 = 600;
+const BENCHMARK_SPAWN_POINTS: u32 = 12;
+const BENCHMARK_SPAWN_RING: f32 = 18.0;
+const BENCHMARK_WARMUP: f32 = 5.0;
+const BENCHMARK_DURATION: f32 = 30.0;
+
+//==============================================================================================
+//        Benchmark Plugin
+//==============================================================================================
+
+/// Floods the arena with minions and reports frame times once the run is over, then exits.
+/// Enabled with the `benchmark` feature.
+pub struct BenchmarkPlugin;
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BenchmarkStats>()
+            .add_systems(OnEnter(GameState::InGame), spawn_benchmark_horde.after(PostGameInit))
+            .add_systems(Update, record_benchmark_frames.run_if(in_state(GameState::InGame)))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Benchmark Stats
+//==============================================================================================
+
+#[derive(Resource, Default)]
+pub struct BenchmarkStats {
+    elapsed : f32,
+    frame_times : Vec<f32>,
+    peak_enemies : u32,
+}
+
+impl BenchmarkStats {
+    fn percentile(sorted : &[f32], percentile : f32) -> f32 {
+        if sorted.is_empty() { return 0.0 }
+        let index = ((sorted.len() - 1) as f32 * percentile).round() as usize;
+        sorted[index]
+    }
+
+    fn report(&self) {
+        let mut sorted = self.frame_times.clone();
+        sorted.sort_by(f32::total_cmp);
+        let average = sorted.iter().sum::<f32>() / sorted.len().max(1) as f32;
+
+        info!(
+            "Benchmark finished: {} frames with up to {} enemies | avg {:.2}ms ({:.0} fps) | p95 {:.2}ms | p99 {:.2}ms | worst {:.2}ms",
+            sorted.len(),
+            self.peak_enemies,
+            average * 1000.0,
+            1.0 / average.max(f32::EPSILON),
+            Self::percentile(&sorted, 0.95) * 1000.0,
+            Self::percentile(&sorted, 0.99) * 1000.0,
+            sorted.last().copied().unwrap_or_default() * 1000.0,
+        );
+    }
+}
+
+//==============================================================================================
+//        Benchmark Systems
+//==============================================================================================
+
+pub fn spawn_benchmark_horde(
+    mut commands : Commands,
+) {
+    let per_spawn_point = BENCHMARK_ENEMIES / BENCHMARK_SPAWN_POINTS;
+    for index in 0..BENCHMARK_SPAWN_POINTS {
+        let angle = TAU * index as f32 / BENCHMARK_SPAWN_POINTS as f32;
+        let position = Vec2::from_angle(angle) * BENCHMARK_SPAWN_RING;
+        commands.trigger(SpawnEnemiesEventBuilder::new(Vec3::new(position.x, 0.0, position.y))
+            .with_weight(EnemyType::Minion, 1)
+            .with_number_of_enemies(per_spawn_point)
+            .build()
+        );
+    }
+}
+
+pub fn record_benchmark_frames(
+    mut stats : ResMut<BenchmarkStats>,
+    enemy_count : Res<EnemyCount>,
+    time : Res<Time>,
+    mut exit : EventWriter<AppExit>,
+) {
+    stats.elapsed += time.delta_secs();
+    if stats.elapsed < BENCHMARK_WARMUP { return }
+
+    stats.frame_times.push(time.delta_secs());
+    stats.peak_enemies = stats.peak_enemies.max(enemy_count.count());
+
+    if stats.elapsed >= BENCHMARK_WARMUP + BENCHMARK_DURATION {
+        stats.report();
+        exit.write(AppExit::Success);
+    }
+}
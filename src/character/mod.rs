@@ -1,18 +1,19 @@
 use std::f64::consts::PI;
 
 use aim::{AimPlugin, ShootTarget};
-use avian3d::{dynamics::rigid_body, prelude::{Collider, Friction, LinearVelocity, LockedAxes, RigidBody}};
+use avian3d::{dynamics::rigid_body, prelude::{Collider, CollisionLayers, LayerMask, Friction, LinearVelocity, LockedAxes, RigidBody}};
 use bevy::{input::mouse::MouseWheel, math::VectorSpace, prelude::*};
 use bevy_enhanced_input::prelude::*;
 use bevy_tnua::{controller, prelude::{TnuaBuiltinWalk, TnuaController}, TnuaNotPlatform};
 use bevy_tnua_avian3d::TnuaAvian3dSensorShape;
 
-use crate::{assets::WizardAssets, camera::{CameraFocus, CameraTarget}, spells::{CastSpell, Spellbook}, GameState};
+use crate::{arena::{definition::CurrentArena, editor::{is_editing, ArenaEditor}, flow_field::FlowFieldTarget}, assets::WizardAssets, camera::{CameraFocus, CameraTarget}, enemy::threat::{TargetKind, Targetable}, spells::{CastSpell, Spellbook}, util::{GameCollisionLayer, Health}, GameState};
 
 pub mod aim;
 
 pub const PLAYER_HEALTH: f32 = 100.0;
 pub const PLAYER_SPEED: f32 = 4.0;
+pub const PLAYER_MANA: f32 = 100.0;
 
 //==============================================================================================
 //        PlayerCharacterPlugin
@@ -55,6 +56,32 @@ pub struct PlayerCharacter {
 #[derive(Component)]
 pub struct PlayerModelRoot;
 
+#[derive(Component)]
+pub struct Mana {
+    pub max_mana: f32,
+    pub current_mana: f32,
+}
+
+impl Mana {
+    pub fn new(max_mana: f32) -> Self {
+        Mana {
+            max_mana,
+            current_mana: max_mana,
+        }
+    }
+    
+    pub fn restore(&mut self, amount: f32) {
+        self.current_mana = (self.current_mana + amount).min(self.max_mana);
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Experience(pub u32);
+
+/// Rare spell scrolls the player has picked up and not spent yet.
+#[derive(Component, Default)]
+pub struct SpellScrolls(pub u32);
+
 impl Default for PlayerCharacter {
     fn default() -> Self {
         PlayerCharacter {
@@ -142,16 +169,21 @@ fn zoom_cam(
 fn cast_spell (
     _trigger : Trigger<Fired<EvokeSpell>>,
     mut commands : Commands,
+    player : Single<Entity, With<PlayerCharacter>>,
     shoot_origin : Single<&Transform, With<ShootOrigin>>,
     shoot_target : Single<&Transform, With<ShootTarget>>,
     spellbook : Res<Spellbook>,
+    editor : Option<Res<ArenaEditor>>,
     mut gizmos : Gizmos
 ) {
+    // Clicks belong to the arena editor while it is open.
+    if is_editing(editor) { return }
     let target_2d = Vec2::new(shoot_target.translation.x, shoot_target.translation.z);
     let origin_2d = Vec2::new(shoot_origin.translation.x, shoot_origin.translation.z);
     let direction = (target_2d - origin_2d).normalize_or_zero();    gizmos.ray(shoot_origin.translation, Vec3::new(direction.x, 0.0, direction.y), Color::srgb(1.0, 0.0, 0.0));
     if spellbook.cooldown.finished() {
         commands.trigger(CastSpell {
+            caster: *player,
             position: shoot_origin.translation,
             direction,
             spell_index: 0,
@@ -165,16 +197,20 @@ fn cast_spell (
 
 pub fn setup_player(
     mut commands : Commands,
-    wizards_assets : Res<WizardAssets>
+    wizards_assets : Res<WizardAssets>,
+    arena : Res<CurrentArena>,
 ) {
     commands.spawn((
         ShootOrigin, 
-        Transform::from_translation(Vec3::new(-2.5, AIM_HEIGHT, 0.0)), 
+        Transform::from_translation(Vec3::new(arena.player_start.x, AIM_HEIGHT, arena.player_start.y)), 
         CameraTarget,
         RigidBody::Dynamic,
         Collider::capsule(0.5, 0.5),
         Actions::<OnFoot>::default(),
         PlayerCharacter::default(),
+        (Health::new(PLAYER_HEALTH), Mana::new(PLAYER_MANA), Experience::default(), SpellScrolls::default()),
+        CollisionLayers::new(GameCollisionLayer::Player, LayerMask::ALL),
+        (FlowFieldTarget, Targetable::new(TargetKind::Player)),
         Name::new("Player"),
         TnuaNotPlatform,
         TnuaController::default(),
@@ -0,0 +1,248 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{arena::flow_field::FlowFieldTarget, assets::{EnemyAnimationGraphs, EnemyAssets}, enemy::{death::Dying, hit_reaction::Staggered, minion::{manage_minion_animation, on_minion_scene_added, GroundWalker, MinionRunAnimations}, perception::{pursue, Awareness, Perception}, threat::{CurrentTarget, TargetKind, TargetPriorities, Targetable}, EnemyBehavior, EnemyType, SpawnEnemy, SpecialEnemyBehavior}, lod::{Lod, LodFrame}, spells::damage::{spawn_damage_box, DamageVolume}, steering::{self, SteeringAgent}, util::{GameCollisionLayer, Health}};
+
+const BOMBER_HEIGHT: f32 = 1.0;
+const BOMBER_RADIUS: f32 = 0.5;
+const BOMBER_HEALTH: f32 = 4.0;
+const BOMBER_SPEED: f32 = 4.0;
+const BOMBER_FUSE: f32 = 1.2;
+/// How close the bomber has to get to its target before it lights the fuse.
+const BOMBER_FUSE_RANGE: f32 = 2.0;
+const BOMBER_BLAST_RADIUS: f32 = 3.5;
+const BOMBER_BLAST_DAMAGE: f32 = 20.0;
+const BOMBER_SIGHT_RANGE: f32 = 10.0;
+const BOMBER_FIELD_OF_VIEW: f32 = 2.4;
+const BOMBER_AWARENESS_RADIUS: f32 = 2.5;
+const BOMBER_MEMORY: f32 = 3.0;
+const BOMBER_OBSTACLE_LOOK_AHEAD: f32 = 2.0;
+const BOMBER_KNOCKBACK: f32 = 5.0;
+const BOMBER_STAGGER: f32 = 0.3;
+const BOMBER_HIT_FLASH: f32 = 0.12;
+const BOMBER_CORPSE_DURATION: f32 = 2.0;
+const BOMBER_PLAYER_PRIORITY: f32 = 3.0;
+const BOMBER_BEACON_PRIORITY: f32 = 2.0;
+const BOMBER_WALKER: GroundWalker = GroundWalker {
+    height : BOMBER_HEIGHT,
+    radius : BOMBER_RADIUS,
+    speed : BOMBER_SPEED,
+    knockback : BOMBER_KNOCKBACK,
+    stagger : BOMBER_STAGGER,
+    hit_flash : BOMBER_HIT_FLASH,
+    corpse_duration : BOMBER_CORPSE_DURATION,
+};
+
+//==============================================================================================
+//        Bomber Plugin
+//==============================================================================================
+
+pub struct BomberPlugin;
+
+impl Plugin for BomberPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BomberAssets>()
+            .add_observer(spawn_bomber)
+            .add_systems(Update, (bomber_attack, burn_fuses, manage_minion_animation::<Bomber>).chain().in_set(SpecialEnemyBehavior))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Bomber Components
+//==============================================================================================
+
+/// Runs at the player or a beacon and blows itself up once it is close enough. The blast hurts
+/// everything caught in it, other enemies included. Killing it before the fuse is lit defuses it.
+#[derive(Component, Clone, Debug)]
+pub struct Bomber {
+    pub fuse : f32,
+    pub fuse_range : f32,
+    pub blast_radius : f32,
+    pub blast_damage : f32,
+}
+
+impl Default for Bomber {
+    fn default() -> Self {
+        Bomber {
+            fuse : BOMBER_FUSE,
+            fuse_range : BOMBER_FUSE_RANGE,
+            blast_radius : BOMBER_BLAST_RADIUS,
+            blast_damage : BOMBER_BLAST_DAMAGE,
+        }
+    }
+}
+
+/// A bomber that has lit its fuse. It stands still until it goes off.
+#[derive(Component)]
+pub struct Fuse(pub Timer);
+
+#[derive(Resource)]
+pub struct BomberAssets {
+    bomb_mesh : Handle<Mesh>,
+    bomb_material : Handle<StandardMaterial>,
+    telegraph_mesh : Handle<Mesh>,
+    telegraph_material : Handle<StandardMaterial>,
+}
+
+impl FromWorld for BomberAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let bomb_mesh = meshes.add(Sphere::new(0.3));
+        let telegraph_mesh = meshes.add(Cylinder::new(1.0, 0.02));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        BomberAssets {
+            bomb_mesh,
+            bomb_material : materials.add(StandardMaterial {
+                base_color : Color::srgb(1.0, 0.4, 0.1),
+                emissive : LinearRgba::rgb(4.0, 1.2, 0.2),
+                ..default()
+            }),
+            telegraph_mesh,
+            telegraph_material : materials.add(StandardMaterial {
+                base_color : Color::srgba(1.0, 0.4, 0.0, 0.4),
+                alpha_mode : AlphaMode::Blend,
+                unlit : true,
+                ..default()
+            }),
+        }
+    }
+}
+
+//==============================================================================================
+//        Spawn a Bomber
+//==============================================================================================
+
+pub fn spawn_bomber(
+    trigger : Trigger<SpawnEnemy>,
+    mut commands : Commands,
+    assets : Res<BomberAssets>,
+    enemy_assets : Res<EnemyAssets>,
+    enemy_animation_graphs : Res<EnemyAnimationGraphs>,
+) {
+    if trigger.enemy_type != EnemyType::Bomber { return }
+    let position = trigger.position;
+
+    let mut bomber = commands.spawn((
+        Name::new("Bomber"),
+        BOMBER_WALKER.living(position, &enemy_animation_graphs),
+        BOMBER_WALKER.body(&enemy_assets, &enemy_animation_graphs),
+        (Bomber::default(), EnemyType::Bomber),
+        Health::new(BOMBER_HEALTH),
+        TargetPriorities::none()
+            .with_priority(TargetKind::Player, BOMBER_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Beacon, BOMBER_BEACON_PRIORITY, None),
+        Perception::new(BOMBER_SIGHT_RANGE, BOMBER_FIELD_OF_VIEW, BOMBER_MEMORY)
+            .with_awareness_radius(BOMBER_AWARENESS_RADIUS),
+        children![(
+            Mesh3d(assets.bomb_mesh.clone()),
+            MeshMaterial3d(assets.bomb_material.clone()),
+            Transform::from_xyz(0.0, 0.1, -(BOMBER_RADIUS + 0.1)),
+        )],
+    ));
+    // The bomb is all it carries, so it goes without the minion's blade.
+    bomber.observe(on_minion_scene_added);
+
+    trigger.insert_extras(&mut bomber);
+}
+
+//==============================================================================================
+//        Bomber Behavior
+//==============================================================================================
+
+/// Runs at the target, following the flow field of targets that have one, and lights the fuse
+/// once it is within range. The blast is centered on the bomber and telegraphed for the length
+/// of the fuse.
+pub fn bomber_attack(
+    mut commands : Commands,
+    mut bombers : Query<(Entity, &Transform, &Bomber, &mut EnemyBehavior, &mut SteeringAgent, &CurrentTarget, Option<&Awareness>, &Lod), (Without<Staggered>, Without<Fuse>)>,
+    targets : Query<(&GlobalTransform, &Targetable, Has<FlowFieldTarget>)>,
+    assets : Res<BomberAssets>,
+    spatial_query : SpatialQuery,
+    lod_frame : Res<LodFrame>,
+) {
+    let obstacle_filter = steering::obstacle_filter();
+
+    for (entity, transform, bomber, mut behavior, mut agent, target, awareness, lod) in bombers.iter_mut() {
+        if !matches!(behavior.as_ref(), EnemyBehavior::Idle | EnemyBehavior::Approach(..) | EnemyBehavior::AttackTarget) { continue }
+        if !lod_frame.should_update(entity, lod) { continue }
+
+        let Some((target_entity, (target_transform, targetable, has_flow_field))) = target.entity().and_then(|target| targets.get(target).ok().map(|found| (target, found))) else {
+            *behavior = EnemyBehavior::Idle;
+            continue;
+        };
+
+        let next = if targetable.kind.is_landmark() { EnemyBehavior::AttackTarget } else { pursue(target_entity, awareness) };
+        if !next.is_attack_target() {
+            *behavior = next;
+            continue;
+        }
+
+        let position = transform.translation.xz();
+        let target_position = target_transform.translation().xz();
+        if position.distance(target_position) <= bomber.fuse_range {
+            agent.stop();
+            *behavior = EnemyBehavior::AttackTarget;
+            commands.entity(entity).insert(Fuse(Timer::from_seconds(bomber.fuse, TimerMode::Once)));
+
+            let volume = DamageVolume::new(
+                bomber.blast_damage,
+                Collider::cylinder(bomber.blast_radius, 2.0),
+                [GameCollisionLayer::Player, GameCollisionLayer::Enemy, GameCollisionLayer::Obstacle],
+                bomber.fuse,
+            ).with_source(entity);
+            spawn_damage_box(&mut commands, volume, Transform::from_xyz(position.x, 1.0, position.y))
+                .with_child((
+                    Mesh3d(assets.telegraph_mesh.clone()),
+                    MeshMaterial3d(assets.telegraph_material.clone()),
+                    Transform::from_xyz(0.0, -0.95, 0.0).with_scale(Vec3::new(bomber.blast_radius, 1.0, bomber.blast_radius)),
+                ));
+            continue;
+        }
+
+        if has_flow_field {
+            // The flow field gets it around obstacles, the approach ends a little inside the fuse range.
+            if !matches!(behavior.as_ref(), EnemyBehavior::Approach(approaching, _) if *approaching == target_entity) {
+                *behavior = EnemyBehavior::approach(target_entity, bomber.fuse_range * 0.5);
+            }
+            continue;
+        }
+
+        *behavior = EnemyBehavior::AttackTarget;
+        let move_vector = steering::arrive(position, target_position, agent.max_speed, bomber.fuse_range * 0.5);
+        let avoid_vector = steering::obstacle_avoidance(&spatial_query, transform.translation, move_vector, BOMBER_OBSTACLE_LOOK_AHEAD, &obstacle_filter);
+        agent.steer(move_vector + avoid_vector);
+        agent.face(target_position - position);
+    }
+}
+
+/// Holds lit bombers in place until they go off. The blast is meant to take the bomber with it,
+/// the health is zeroed as well so armor or a shield can't leave it standing.
+pub fn burn_fuses(
+    mut bombers : Query<(&mut Fuse, &mut SteeringAgent, &mut Health), Without<Dying>>,
+    time : Res<Time>,
+) {
+    for (mut fuse, mut agent, mut health) in bombers.iter_mut() {
+        agent.stop();
+        fuse.0.tick(time.delta());
+        if fuse.0.finished() {
+            health.current_health = 0.0;
+        }
+    }
+}
+
+//==============================================================================================
+//        Animating the Bomber
+//==============================================================================================
+
+/// Bombers sprint with their whole body, they have nothing to swing.
+impl MinionRunAnimations for Bomber {
+    fn run(animations : &EnemyAnimationGraphs) -> AnimationNodeIndex {
+        animations.minion_run_fast
+    }
+
+    fn run_upper_body(_animations : &EnemyAnimationGraphs) -> Option<AnimationNodeIndex> {
+        None
+    }
+}
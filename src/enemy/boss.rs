@@ -0,0 +1,549 @@
+use std::f32::consts::{PI, TAU};
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_tnua::{prelude::*, TnuaNotPlatform};
+use bevy_tnua_avian3d::TnuaAvian3dSensorShape;
+
+use crate::{arena::ArenaProp, assets::{EnemyAnimationGraphs, EnemyAssets}, character::PlayerCharacter, enemy::{death::{DeathSequence, EnemyDied}, hit_reaction::{HitReaction, Staggered}, minion::{manage_minion_animation, Casting, MinionRunAnimations}, Enemy, EnemyBehavior, EnemySpawnAnimationComplete, EnemyType, SpawnEnemiesEventBuilder, SpawnEnemy, SpecialEnemyBehavior}, spells::damage::{spawn_damage_box, Damage, DamageVolume}, steering::{self, SteeringAgent}, util::{AnimatedModelFor, AnimatedSceneCreated, GameCollisionLayer, Health, SceneRootWithAnimation}};
+
+const BOSS_SCALE: f32 = 2.5;
+const BOSS_HEIGHT: f32 = 2.5;
+const BOSS_RADIUS: f32 = 1.2;
+const BOSS_HEALTH: f32 = 300.0;
+const BOSS_SPEED: f32 = 2.0;
+const BOSS_PREFERRED_RANGE: f32 = 6.0;
+const BOSS_CAST_TIME: f32 = 0.6;
+const BOSS_CORPSE_DURATION: f32 = 8.0;
+const BOSS_ARENA_RADIUS: f32 = 14.0;
+const BOSS_ARENA_SEGMENTS: usize = 32;
+const BOSS_ARENA_WALL_HEIGHT: f32 = 3.0;
+/// How far inside of the ring the player is kept, so the walls never go up on top of them.
+const BOSS_ARENA_PLAYER_MARGIN: f32 = 4.0;
+const BOSS_BARRAGE_SPREAD: f32 = 3.0;
+
+//==============================================================================================
+//        Boss Plugin
+//==============================================================================================
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BossAssets>()
+
+            .add_event::<BossEvent>()
+
+            .add_observer(spawn_skeleton_mage_boss)
+            .add_observer(start_boss_fight)
+            .add_observer(end_boss_fight)
+            .add_observer(remove_boss_barriers)
+
+            .add_systems(Update, (advance_boss_phases, boss_behavior, report_boss_health, manage_minion_animation::<Boss>, play_boss_spellcast).chain().in_set(SpecialEnemyBehavior))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Boss Components
+//==============================================================================================
+
+/// A boss moves through its phases as its health drops, every phase has its own attack pattern.
+#[derive(Component, Debug)]
+pub struct Boss {
+    pub name : String,
+    pub phases : Vec<BossPhase>,
+    pub phase : usize,
+    pub next_attack : usize,
+    pub attack_cooldown : Timer,
+    pub base_speed : f32,
+}
+
+impl Boss {
+    pub fn new(name : impl Into<String>, base_speed : f32, phases : Vec<BossPhase>) -> Self {
+        let attack_interval = phases.first().map(|phase| phase.attack_interval).unwrap_or(1.0);
+        Boss {
+            name : name.into(),
+            phases,
+            phase : 0,
+            next_attack : 0,
+            attack_cooldown : Timer::from_seconds(attack_interval, TimerMode::Once),
+            base_speed,
+        }
+    }
+
+    pub fn current_phase(&self) -> Option<&BossPhase> {
+        self.phases.get(self.phase)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BossPhase {
+    /// The phase starts once the boss is at or below this fraction of its max health.
+    pub health_threshold : f32,
+    pub attack_interval : f32,
+    pub speed_multiplier : f32,
+    /// Played in order, starting over once the end is reached.
+    pub pattern : Vec<BossAttack>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BossAttack {
+    /// Telegraphed circles dropped on and around the player.
+    Barrage { count : u32, radius : f32, damage : f32, telegraph : f32 },
+    /// A telegraphed circle centered on the boss itself.
+    Nova { radius : f32, damage : f32, telegraph : f32 },
+    Summon { enemy_type : EnemyType, count : u32 },
+    /// Runs straight at where the player was, hurting them once if it runs into them.
+    Charge { speed : f32, duration : f32, damage : f32 },
+}
+
+#[derive(Component, Debug, Default)]
+pub enum BossAction {
+    #[default]
+    Pursuing,
+    Casting(Timer),
+    Charging { direction : Vec2, timer : Timer, damage : f32, has_hit : bool },
+}
+
+/// A wall of the ring that keeps the player inside of the fight until the boss is dead.
+#[derive(Component)]
+pub struct BossArenaBarrier {
+    pub boss : Entity,
+}
+
+//==============================================================================================
+//        Boss Events
+//==============================================================================================
+
+/// Everything a boss health bar needs to know about the fight.
+#[derive(Event, Clone, Debug)]
+pub enum BossEvent {
+    Started { boss : Entity, name : String, max_health : f32 },
+    HealthChanged { boss : Entity, current : f32, max : f32 },
+    PhaseChanged { boss : Entity, phase : usize },
+    Defeated { boss : Entity },
+}
+
+#[derive(Resource)]
+pub struct BossAssets {
+    telegraph_mesh : Handle<Mesh>,
+    telegraph_material : Handle<StandardMaterial>,
+    barrier_mesh : Handle<Mesh>,
+    barrier_material : Handle<StandardMaterial>,
+}
+
+impl FromWorld for BossAssets {
+    fn from_world(world: &mut World) -> Self {
+        let segment_length = TAU * BOSS_ARENA_RADIUS / BOSS_ARENA_SEGMENTS as f32;
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let telegraph_mesh = meshes.add(Cylinder::new(1.0, 0.02));
+        let barrier_mesh = meshes.add(Cuboid::new(segment_length * 1.05, BOSS_ARENA_WALL_HEIGHT, 0.2));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        BossAssets {
+            telegraph_mesh,
+            telegraph_material : materials.add(StandardMaterial {
+                base_color : Color::srgba(1.0, 0.1, 0.0, 0.4),
+                alpha_mode : AlphaMode::Blend,
+                unlit : true,
+                ..default()
+            }),
+            barrier_mesh,
+            barrier_material : materials.add(StandardMaterial {
+                base_color : Color::srgba(0.5, 0.2, 1.0, 0.25),
+                alpha_mode : AlphaMode::Blend,
+                unlit : true,
+                ..default()
+            }),
+        }
+    }
+}
+
+//==============================================================================================
+//        Spawn the Skeleton Mage Boss
+//==============================================================================================
+
+fn skeleton_mage_phases() -> Vec<BossPhase> {
+    vec![
+        BossPhase {
+            health_threshold : 1.0,
+            attack_interval : 3.0,
+            speed_multiplier : 1.0,
+            pattern : vec![
+                BossAttack::Barrage { count: 1, radius: 2.5, damage: 15.0, telegraph: 1.2 },
+                BossAttack::Summon { enemy_type: EnemyType::Minion, count: 3 },
+                BossAttack::Barrage { count: 3, radius: 2.0, damage: 10.0, telegraph: 1.2 },
+            ],
+        },
+        BossPhase {
+            health_threshold : 0.6,
+            attack_interval : 2.5,
+            speed_multiplier : 1.25,
+            pattern : vec![
+                BossAttack::Charge { speed: 12.0, duration: 1.0, damage: 20.0 },
+                BossAttack::Barrage { count: 4, radius: 2.0, damage: 10.0, telegraph: 1.0 },
+                BossAttack::Nova { radius: 5.0, damage: 20.0, telegraph: 1.0 },
+            ],
+        },
+        BossPhase {
+            health_threshold : 0.25,
+            attack_interval : 1.75,
+            speed_multiplier : 1.5,
+            pattern : vec![
+                BossAttack::Summon { enemy_type: EnemyType::Minion, count: 5 },
+                BossAttack::Charge { speed: 14.0, duration: 1.0, damage: 25.0 },
+                BossAttack::Nova { radius: 6.0, damage: 25.0, telegraph: 0.8 },
+                BossAttack::Barrage { count: 6, radius: 2.0, damage: 10.0, telegraph: 0.8 },
+            ],
+        },
+    ]
+}
+
+pub fn spawn_skeleton_mage_boss(
+    trigger : Trigger<SpawnEnemy>,
+    mut commands : Commands,
+    enemy_assets : Res<EnemyAssets>,
+    enemy_animation_graphs : Res<EnemyAnimationGraphs>
+) {
+    if trigger.enemy_type != EnemyType::SkeletonMageBoss { return }
+    let position = trigger.position;
+
+    let mut boss = commands.spawn((
+        Name::new("Skeleton Mage Boss"),
+        Transform::from_translation(Vec3::new(position.x, BOSS_HEIGHT, position.z)),
+        (
+            Boss::new("Skeleton Mage", BOSS_SPEED, skeleton_mage_phases()),
+            BossAction::default(),
+            EnemyType::SkeletonMageBoss,
+        ),
+        Enemy {
+            height_from_ground : BOSS_HEIGHT,
+            speed : BOSS_SPEED,
+        },
+        Health::new(BOSS_HEALTH),
+        EnemyBehavior::Spawning,
+        CollisionLayers::new(GameCollisionLayer::Enemy, [
+            GameCollisionLayer::Player,
+            GameCollisionLayer::Default,
+            GameCollisionLayer::Spell,
+            GameCollisionLayer::Obstacle,
+        ]),
+        RigidBody::Dynamic,
+        Collider::capsule(BOSS_RADIUS, BOSS_HEIGHT),
+        TnuaController::default(),
+        SteeringAgent::new(BOSS_RADIUS, BOSS_SPEED),
+        (
+            HitReaction {
+                flash_duration : 0.1,
+                ..default()
+            },
+            DeathSequence {
+                animation : Some(enemy_animation_graphs.minion_death),
+                collapse : false,
+                despawn_delay : BOSS_CORPSE_DURATION,
+            },
+        ),
+        TnuaAvian3dSensorShape(Collider::cylinder(BOSS_RADIUS - 0.01, 0.0)),
+        TnuaNotPlatform,
+        SceneRootWithAnimation::new(enemy_assets.skeleton_mage.clone())
+            .with_animation_graph(enemy_animation_graphs.minion_graph.clone())
+            .with_animation(enemy_animation_graphs.minion_spawn)
+            .with_transform(Transform::from_translation((0.0, -BOSS_HEIGHT, 0.0).into())
+                .with_rotation(Quat::from_rotation_y(PI))
+                .with_scale(Vec3::splat(BOSS_SCALE))
+            ),
+    ));
+    boss.observe(on_boss_scene_added);
+
+    trigger.insert_extras(&mut boss);
+}
+
+pub fn on_boss_scene_added(
+    trigger : Trigger<AnimatedSceneCreated>,
+    mut commands : Commands,
+) {
+    let controler = trigger.target();
+    commands.entity(trigger.0).observe(move |_ : Trigger<EnemySpawnAnimationComplete>, mut enemies : Query<&mut EnemyBehavior, With<Boss>>| {
+        let Ok(mut enemy_behavior) = enemies.get_mut(controler) else { return; };
+        *enemy_behavior = EnemyBehavior::Idle;
+    });
+}
+
+//==============================================================================================
+//        Boss Fight
+//==============================================================================================
+
+/// Locks the player into a ring with the boss as soon as it shows up. The ring sits between the two
+/// of them, but never so far from the player that they end up outside of it or under a wall.
+pub fn start_boss_fight(
+    trigger : Trigger<OnAdd, Boss>,
+    mut commands : Commands,
+    bosses : Query<(&Boss, &Transform, Option<&Health>)>,
+    player : Query<&Transform, With<PlayerCharacter>>,
+    assets : Res<BossAssets>,
+    mut boss_events : EventWriter<BossEvent>,
+) {
+    let entity = trigger.target();
+    let Ok((boss, transform, health)) = bosses.get(entity) else { return };
+
+    boss_events.write(BossEvent::Started {
+        boss : entity,
+        name : boss.name.clone(),
+        max_health : health.map(|health| health.max_health).unwrap_or_default(),
+    });
+
+    let boss_position = transform.translation.xz();
+    let center = player.single().ok()
+        .map(|player| player.translation.xz())
+        .map(|player| player + ((boss_position - player) / 2.0).clamp_length_max(BOSS_ARENA_RADIUS - BOSS_ARENA_PLAYER_MARGIN))
+        .unwrap_or(boss_position);
+    let center = Vec3::new(center.x, BOSS_ARENA_WALL_HEIGHT / 2.0, center.y);
+    for segment in 0..BOSS_ARENA_SEGMENTS {
+        let angle = TAU * segment as f32 / BOSS_ARENA_SEGMENTS as f32;
+        let offset = Vec3::new(angle.cos(), 0.0, angle.sin()) * BOSS_ARENA_RADIUS;
+        commands.spawn((
+            Name::new("Boss Arena Barrier"),
+            BossArenaBarrier { boss: entity },
+            ArenaProp,
+            Transform::from_translation(center + offset).looking_to(offset, Vec3::Y),
+            Mesh3d(assets.barrier_mesh.clone()),
+            MeshMaterial3d(assets.barrier_material.clone()),
+            RigidBody::Static,
+            Collider::cuboid(TAU * BOSS_ARENA_RADIUS / BOSS_ARENA_SEGMENTS as f32 * 1.05, BOSS_ARENA_WALL_HEIGHT, 0.2),
+            // Only the player is held in, enemies walk through the barrier.
+            CollisionLayers::new(GameCollisionLayer::Barrier, GameCollisionLayer::Player),
+        ));
+    }
+}
+
+pub fn end_boss_fight(
+    trigger : Trigger<EnemyDied>,
+    mut commands : Commands,
+    bosses : Query<(), With<Boss>>,
+    barriers : Query<(Entity, &BossArenaBarrier)>,
+    mut boss_events : EventWriter<BossEvent>,
+) {
+    let entity = trigger.target();
+    if !bosses.contains(entity) { return }
+
+    boss_events.write(BossEvent::Defeated { boss: entity });
+    despawn_boss_barriers(&mut commands, &barriers, entity);
+}
+
+/// Takes the ring down when the boss goes away without dying, like when the arena is rebuilt.
+pub fn remove_boss_barriers(
+    trigger : Trigger<OnRemove, Boss>,
+    mut commands : Commands,
+    barriers : Query<(Entity, &BossArenaBarrier)>,
+) {
+    despawn_boss_barriers(&mut commands, &barriers, trigger.target());
+}
+
+fn despawn_boss_barriers(commands : &mut Commands, barriers : &Query<(Entity, &BossArenaBarrier)>, boss : Entity) {
+    for (barrier, _) in barriers.iter().filter(|(_, barrier)| barrier.boss == boss) {
+        // The barriers are arena props as well, so they may already be on their way out.
+        commands.entity(barrier).try_despawn();
+    }
+}
+
+//==============================================================================================
+//        Boss Behavior
+//==============================================================================================
+
+pub fn advance_boss_phases(
+    mut bosses : Query<(Entity, &mut Boss, &Health, &mut Enemy, &mut SteeringAgent, &BossAction)>,
+    mut boss_events : EventWriter<BossEvent>,
+) {
+    for (entity, mut boss, health, mut enemy, mut agent, action) in bosses.iter_mut() {
+        let health_fraction = health.current_health / health.max_health.max(f32::EPSILON);
+        let mut phase = boss.phase;
+        while boss.phases.get(phase + 1).is_some_and(|next| health_fraction <= next.health_threshold) {
+            phase += 1;
+        }
+        if phase == boss.phase { continue; }
+
+        boss.phase = phase;
+        boss.next_attack = 0;
+        let Some(current) = boss.current_phase().cloned() else { continue };
+        boss.attack_cooldown = Timer::from_seconds(current.attack_interval, TimerMode::Once);
+        enemy.speed = boss.base_speed * current.speed_multiplier;
+        // A charge sets its own speed and puts this one back when it is done.
+        if !matches!(action, BossAction::Charging { .. }) {
+            agent.max_speed = enemy.speed;
+        }
+        boss_events.write(BossEvent::PhaseChanged { boss: entity, phase });
+    }
+}
+
+pub fn boss_behavior(
+    mut commands : Commands,
+    mut bosses : Query<(Entity, &Transform, &mut Boss, &mut BossAction, &mut SteeringAgent, &Enemy, &EnemyBehavior), Without<Staggered>>,
+    player : Single<(Entity, &Transform), (With<PlayerCharacter>, Without<Boss>)>,
+    assets : Res<BossAssets>,
+    time : Res<Time>,
+) {
+    let (player_entity, player_transform) = *player;
+    let player_position = player_transform.translation.xz();
+
+    for (entity, transform, mut boss, mut action, mut agent, enemy, behavior) in bosses.iter_mut() {
+        if matches!(behavior, EnemyBehavior::Spawning) { continue; }
+        let position = transform.translation.xz();
+
+        match action.as_mut() {
+            BossAction::Pursuing => {
+                if position.distance(player_position) > BOSS_PREFERRED_RANGE {
+                    let max_speed = agent.max_speed;
+                    agent.steer(steering::arrive(position, player_position, max_speed, BOSS_PREFERRED_RANGE));
+                } else {
+                    agent.stop();
+                }
+                agent.face(player_position - position);
+
+                boss.attack_cooldown.tick(time.delta());
+                if !boss.attack_cooldown.finished() { continue; }
+                boss.attack_cooldown.reset();
+
+                let Some(phase) = boss.current_phase() else { continue };
+                let Some(attack) = phase.pattern.get(boss.next_attack % phase.pattern.len().max(1)).copied() else { continue };
+                boss.next_attack += 1;
+
+                *action = perform_boss_attack(&mut commands, &assets, entity, transform.translation, player_position, attack);
+                if matches!(*action, BossAction::Casting(_)) {
+                    commands.entity(entity).insert(Casting);
+                }
+                if let BossAttack::Charge { speed, .. } = attack {
+                    agent.max_speed = speed;
+                }
+            }
+            BossAction::Casting(timer) => {
+                agent.stop();
+                agent.face(player_position - position);
+                timer.tick(time.delta());
+                if timer.finished() {
+                    commands.entity(entity).remove::<Casting>();
+                    *action = BossAction::Pursuing;
+                }
+            }
+            BossAction::Charging { direction, timer, damage, has_hit } => {
+                let max_speed = agent.max_speed;
+                agent.steer(*direction * max_speed);
+                if !*has_hit && position.distance(player_position) <= agent.radius + 1.0 {
+                    commands.trigger_targets(Damage::from_source(*damage, entity), player_entity);
+                    *has_hit = true;
+                }
+
+                timer.tick(time.delta());
+                if timer.finished() {
+                    agent.max_speed = enemy.speed;
+                    *action = BossAction::Pursuing;
+                }
+            }
+        }
+    }
+}
+
+fn perform_boss_attack(commands : &mut Commands, assets : &BossAssets, boss : Entity, position : Vec3, player_position : Vec2, attack : BossAttack) -> BossAction {
+    match attack {
+        BossAttack::Barrage { count, radius, damage, telegraph } => {
+            for index in 0..count {
+                // The first circle always lands right on the player, the rest spread out around them.
+                let offset = if index == 0 { Vec2::ZERO } else {
+                    Vec2::from_angle(TAU * index as f32 / (count - 1) as f32) * BOSS_BARRAGE_SPREAD
+                };
+                spawn_telegraph(commands, assets, boss, player_position + offset, radius, damage, telegraph);
+            }
+            BossAction::Casting(Timer::from_seconds(BOSS_CAST_TIME.max(telegraph), TimerMode::Once))
+        }
+        BossAttack::Nova { radius, damage, telegraph } => {
+            spawn_telegraph(commands, assets, boss, position.xz(), radius, damage, telegraph);
+            BossAction::Casting(Timer::from_seconds(telegraph, TimerMode::Once))
+        }
+        BossAttack::Summon { enemy_type, count } => {
+            commands.trigger(SpawnEnemiesEventBuilder::new(Vec3::new(position.x, 0.0, position.z))
+                .with_weight(enemy_type, 1)
+                .with_number_of_enemies(count)
+                .build()
+            );
+            BossAction::Casting(Timer::from_seconds(BOSS_CAST_TIME, TimerMode::Once))
+        }
+        BossAttack::Charge { duration, damage, .. } => {
+            BossAction::Charging {
+                direction : (player_position - position.xz()).normalize_or_zero(),
+                timer : Timer::from_seconds(duration, TimerMode::Once),
+                damage,
+                has_hit : false,
+            }
+        }
+    }
+}
+
+fn spawn_telegraph(commands : &mut Commands, assets : &BossAssets, boss : Entity, at : Vec2, radius : f32, damage : f32, telegraph : f32) {
+    let volume = DamageVolume::new(damage, Collider::cylinder(radius, 2.0), GameCollisionLayer::Player, telegraph).with_source(boss);
+    spawn_damage_box(commands, volume, Transform::from_xyz(at.x, 1.0, at.y))
+        .with_child((
+            Mesh3d(assets.telegraph_mesh.clone()),
+            MeshMaterial3d(assets.telegraph_material.clone()),
+            Transform::from_xyz(0.0, -0.95, 0.0).with_scale(Vec3::new(radius, 1.0, radius)),
+        ));
+}
+
+/// Sends the health of every boss that took damage or healed this frame.
+pub fn report_boss_health(
+    bosses : Query<(Entity, &Health), (With<Boss>, Changed<Health>)>,
+    mut boss_events : EventWriter<BossEvent>,
+) {
+    for (entity, health) in bosses.iter() {
+        boss_events.write(BossEvent::HealthChanged {
+            boss : entity,
+            current : health.current_health.max(0.0),
+            max : health.max_health,
+        });
+    }
+}
+
+//==============================================================================================
+//        Animating the Boss
+//==============================================================================================
+
+impl MinionRunAnimations for Boss {
+    fn run(animations : &EnemyAnimationGraphs) -> AnimationNodeIndex {
+        animations.minion_run_bottom
+    }
+
+    fn run_upper_body(animations : &EnemyAnimationGraphs) -> Option<AnimationNodeIndex> {
+        Some(animations.minion_run_top)
+    }
+}
+
+/// Holds the spellcast for as long as the boss is [`Casting`], everything else is left to [`manage_minion_animation`].
+fn play_boss_spellcast(
+    bosses : Query<(), (With<Boss>, With<Casting>)>,
+    mut boss_animated_models : Query<(&AnimatedModelFor, &mut AnimationPlayer)>,
+    animations : Res<EnemyAnimationGraphs>,
+) {
+    for (animated_model_for, mut animation_player) in boss_animated_models.iter_mut() {
+        if !bosses.contains(animated_model_for.0) { continue; }
+        animation_player.stop(animations.minion_run_top);
+        animation_player.stop(animations.minion_run_bottom);
+        animation_player.stop(animations.minion_idle);
+        animation_player.play(animations.minion_spellcast).repeat();
+    }
+}
+
+//==============================================================================================
+//        Debug
+//==============================================================================================
+
+/// Press B to drop the boss in next to the player.
+pub fn debug_spawn_boss(
+    mut commands : Commands,
+    keys : Res<ButtonInput<KeyCode>>,
+    player : Single<&Transform, With<PlayerCharacter>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyB) { return }
+    commands.trigger(SpawnEnemiesEventBuilder::new(player.translation + Vec3::new(0.0, 0.0, 8.0))
+        .with_weight(EnemyType::SkeletonMageBoss, 1)
+        .build()
+    );
+}
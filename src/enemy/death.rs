@@ -0,0 +1,137 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_tnua::prelude::*;
+
+use crate::{enemy::{hit_reaction::{HitReaction, Staggered}, pathing::PathTask, siege::Besieging, orders::Returning, perception::Awareness, squad::{Routed, SquadMember}, threat::{CurrentTarget, ThreatTable}, Enemy, EnemyBehavior, EnemyCount}, pool::{despawn_or_release, Inactive, Pooled}, spells::damage::Damage, steering::SteeringAgent, util::{AnimationControlerFor, GameCollisionLayer, Health}};
+
+const DEFAULT_DESPAWN_DELAY: f32 = 3.0;
+
+//==============================================================================================
+//        Death Plugin
+//==============================================================================================
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_observer(record_last_hit)
+            .add_systems(Update, despawn_corpses)
+            .add_systems(PostUpdate, check_for_dead_enemies)
+        ;
+    }
+}
+
+//==============================================================================================
+//        Death Components
+//==============================================================================================
+
+/// How an enemy type dies.
+#[derive(Component, Clone, Debug)]
+pub struct DeathSequence {
+    /// Played over every other animation once the enemy dies.
+    pub animation : Option<AnimationNodeIndex>,
+    /// Lets the body fall over under physics instead of freezing it in place.
+    pub collapse : bool,
+    /// How long the corpse stays around before it is despawned.
+    pub despawn_delay : f32,
+}
+
+impl Default for DeathSequence {
+    fn default() -> Self {
+        DeathSequence {
+            animation : None,
+            collapse : false,
+            despawn_delay : DEFAULT_DESPAWN_DELAY,
+        }
+    }
+}
+
+/// The source of the last damage an enemy took, this is who gets the credit for the kill.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LastHitBy(pub Entity);
+
+/// A dead enemy that is waiting to be despawned. Its AI, steering and controller are already gone.
+#[derive(Component)]
+pub struct Dying(pub Timer);
+
+//==============================================================================================
+//        Death Event
+//==============================================================================================
+
+/// Triggered on an enemy the moment it dies, before its corpse is despawned.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EnemyDied {
+    pub killer : Option<Entity>,
+    pub position : Vec3,
+}
+
+//==============================================================================================
+//        Death Systems
+//==============================================================================================
+
+pub fn record_last_hit(
+    trigger : Trigger<Damage>,
+    mut commands : Commands,
+    enemies : Query<(), (With<Enemy>, Without<Dying>)>,
+) {
+    let Some(source) = trigger.source else { return };
+    if !enemies.contains(trigger.target()) { return }
+    commands.entity(trigger.target()).insert(LastHitBy(source));
+}
+
+pub fn check_for_dead_enemies (
+    mut commands : Commands,
+    enemies : Query<(Entity, &Health, &Transform, Option<&LastHitBy>, Option<&DeathSequence>, Option<&AnimationControlerFor>), (With<Enemy>, Without<Dying>, Without<Inactive>)>,
+    mut animation_players : Query<&mut AnimationPlayer>,
+    mut enemy_count : ResMut<EnemyCount>
+) {
+    for (entity, health, transform, last_hit_by, death, animated_models) in enemies.iter() {
+        if health.current_health > 0.0 { continue; }
+        let death = death.cloned().unwrap_or_default();
+
+        // `Dying` keeps this enemy out of this query, so it is only ever counted once.
+        enemy_count.0 = enemy_count.0.saturating_sub(1);
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands
+            .remove::<(EnemyBehavior, SteeringAgent, TnuaController, HitReaction, Staggered, Besieging, PathTask, ThreatTable, CurrentTarget, Awareness, Returning, Routed, SquadMember)>()
+            .insert(Dying(Timer::from_seconds(death.despawn_delay, TimerMode::Once)));
+
+        if death.collapse {
+            // Without the controller holding it up the body tips over, it only needs to stay on the ground.
+            entity_commands.insert(CollisionLayers::new(GameCollisionLayer::Enemy, [GameCollisionLayer::Default, GameCollisionLayer::Obstacle]));
+        } else {
+            entity_commands.insert((RigidBody::Kinematic, LinearVelocity::ZERO, AngularVelocity::ZERO, CollisionLayers::NONE));
+        }
+
+        if let (Some(animation), Some(animated_models)) = (death.animation, animated_models) {
+            for model in animated_models.iter() {
+                let Ok(mut player) = animation_players.get_mut(model) else { continue };
+                player.stop_all();
+                player.start(animation);
+            }
+        }
+
+        commands.trigger_targets(EnemyDied {
+            killer : last_hit_by.map(|last_hit_by| last_hit_by.0),
+            position : transform.translation,
+        }, entity);
+    }
+}
+
+pub fn despawn_corpses(
+    mut commands : Commands,
+    mut corpses : Query<(Entity, &mut Dying, Has<Pooled>)>,
+    time : Res<Time>,
+) {
+    for (entity, mut dying, pooled) in corpses.iter_mut() {
+        dying.0.tick(time.delta());
+        if !dying.0.finished() { continue; }
+        
+        if pooled {
+            commands.entity(entity).remove::<Dying>();
+        }
+        despawn_or_release(&mut commands, entity, pooled);
+    }
+}
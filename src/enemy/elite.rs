@@ -0,0 +1,225 @@
+use bevy::prelude::*;
+use rand::seq::IndexedRandom;
+
+use crate::{enemy::{death::{Dying, EnemyDied}, hit_reaction::HitReaction, Enemy}, spells::damage::{Armor, Damage, Shield}, steering::SteeringAgent, util::Health};
+
+pub const MAX_ELITE_AFFIXES: usize = 2;
+const ARMORED_DAMAGE_REDUCTION: f32 = 0.3;
+const VAMPIRIC_LIFE_STEAL: f32 = 0.5;
+const EXPLOSION_RADIUS: f32 = 3.0;
+const EXPLOSION_DAMAGE: f32 = 15.0;
+const SHIELD_FRACTION_OF_HEALTH: f32 = 0.5;
+const SHIELD_RECHARGE_RATE: f32 = 2.0;
+const SHIELD_RECHARGE_DELAY: f32 = 3.0;
+const ELITE_MARKER_RADIUS: f32 = 0.7;
+
+//==============================================================================================
+//        Elite Plugin
+//==============================================================================================
+
+pub struct ElitePlugin;
+
+impl Plugin for ElitePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EliteAssets>()
+
+            .add_observer(apply_elite_affixes)
+            .add_observer(remove_elite_affixes)
+            .add_observer(vampiric_life_steal)
+            .add_observer(explode_on_death)
+        ;
+    }
+}
+
+//==============================================================================================
+//        Affixes
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Affix {
+    Hasted,
+    Armored,
+    Vampiric,
+    ExplodesOnDeath,
+    Shielded,
+}
+
+/// The multipliers an affix applies on top of the enemy's base stats.
+#[derive(Clone, Copy, Debug)]
+pub struct AffixStats {
+    pub health : f32,
+    pub speed : f32,
+    pub knockback : f32,
+}
+
+impl Affix {
+    pub const ALL : [Affix; 5] = [Affix::Hasted, Affix::Armored, Affix::Vampiric, Affix::ExplodesOnDeath, Affix::Shielded];
+
+    pub fn stats(&self) -> AffixStats {
+        match self {
+            Affix::Hasted => AffixStats { health: 1.0, speed: 1.5, knockback: 1.0 },
+            Affix::Armored => AffixStats { health: 2.0, speed: 0.8, knockback: 0.25 },
+            Affix::Vampiric => AffixStats { health: 1.5, speed: 1.0, knockback: 1.0 },
+            Affix::ExplodesOnDeath => AffixStats { health: 1.25, speed: 1.1, knockback: 1.0 },
+            Affix::Shielded => AffixStats { health: 1.0, speed: 1.0, knockback: 0.5 },
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Affix::Hasted => Color::srgb(0.3, 0.9, 1.0),
+            Affix::Armored => Color::srgb(0.6, 0.6, 0.7),
+            Affix::Vampiric => Color::srgb(0.8, 0.0, 0.2),
+            Affix::ExplodesOnDeath => Color::srgb(1.0, 0.5, 0.0),
+            Affix::Shielded => Color::srgb(0.4, 0.5, 1.0),
+        }
+    }
+}
+
+//==============================================================================================
+//        Elite Component
+//==============================================================================================
+
+/// Rolled onto an enemy when it spawns. The affixes are applied as soon as this is added.
+#[derive(Component, Clone, Debug)]
+pub struct Elite {
+    pub affixes : Vec<Affix>,
+}
+
+impl Elite {
+    /// Rolls between one and [`MAX_ELITE_AFFIXES`] different affixes.
+    pub fn roll() -> Self {
+        let mut rng = rand::rng();
+        let count = rand::Rng::random_range(&mut rng, 1..=MAX_ELITE_AFFIXES);
+        Elite { affixes: Affix::ALL.choose_multiple(&mut rng, count).copied().collect() }
+    }
+
+    pub fn has(&self, affix : Affix) -> bool {
+        self.affixes.contains(&affix)
+    }
+}
+
+/// The ring shown at the feet of an elite for each of its affixes.
+#[derive(Component)]
+pub struct EliteMarker;
+
+#[derive(Resource)]
+pub struct EliteAssets {
+    marker : Handle<Mesh>,
+    materials : Vec<(Affix, Handle<StandardMaterial>)>,
+}
+
+impl FromWorld for EliteAssets {
+    fn from_world(world: &mut World) -> Self {
+        let marker = world.resource_mut::<Assets<Mesh>>().add(Torus::new(ELITE_MARKER_RADIUS - 0.08, ELITE_MARKER_RADIUS));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = Affix::ALL.into_iter()
+            .map(|affix| (affix, materials.add(StandardMaterial {
+                base_color : affix.color(),
+                emissive : affix.color().to_linear() * 3.0,
+                unlit : true,
+                ..default()
+            })))
+            .collect();
+        EliteAssets { marker, materials }
+    }
+}
+
+impl EliteAssets {
+    fn material(&self, affix : Affix) -> Handle<StandardMaterial> {
+        self.materials.iter().find(|(other, _)| *other == affix).map(|(_, material)| material.clone()).unwrap_or_default()
+    }
+}
+
+//==============================================================================================
+//        Elite Systems
+//==============================================================================================
+
+pub fn apply_elite_affixes(
+    trigger : Trigger<OnAdd, Elite>,
+    mut commands : Commands,
+    mut enemies : Query<(&Elite, &mut Enemy, Option<&mut Health>, Option<&mut SteeringAgent>, Option<&mut HitReaction>)>,
+    assets : Res<EliteAssets>,
+) {
+    let entity = trigger.target();
+    let Ok((elite, mut enemy, mut health, mut agent, mut reaction)) = enemies.get_mut(entity) else { return };
+
+    for affix in elite.affixes.iter() {
+        let stats = affix.stats();
+        if let Some(health) = health.as_mut() {
+            health.max_health *= stats.health;
+            health.current_health = health.max_health;
+        }
+        enemy.speed *= stats.speed;
+        if let Some(agent) = agent.as_mut() {
+            agent.max_speed *= stats.speed;
+        }
+        if let Some(reaction) = reaction.as_mut() {
+            reaction.knockback *= stats.knockback;
+        }
+    }
+
+    if elite.has(Affix::Armored) {
+        commands.entity(entity).insert(Armor(ARMORED_DAMAGE_REDUCTION));
+    }
+    if elite.has(Affix::Shielded) {
+        let shield = health.map(|health| health.max_health * SHIELD_FRACTION_OF_HEALTH).unwrap_or_default();
+        commands.entity(entity).insert(Shield::new(shield, SHIELD_RECHARGE_RATE, SHIELD_RECHARGE_DELAY));
+    }
+
+    // One ring at the feet per affix, stacked so every affix can be read at a glance.
+    for (index, affix) in elite.affixes.iter().enumerate() {
+        commands.entity(entity).with_child((
+            Name::new("EliteMarker"),
+            EliteMarker,
+            Mesh3d(assets.marker.clone()),
+            MeshMaterial3d(assets.material(*affix)),
+            Transform::from_xyz(0.0, 0.05 + index as f32 * 0.1 - enemy.height_from_ground, 0.0)
+                .with_scale(Vec3::splat(1.0 + index as f32 * 0.2)),
+        ));
+    }
+}
+
+/// Takes away what the affixes added on top of the enemy, so a pooled enemy comes back as a regular one.
+pub fn remove_elite_affixes(
+    trigger : Trigger<OnRemove, Elite>,
+    mut commands : Commands,
+    children : Query<&Children>,
+    markers : Query<(), With<EliteMarker>>,
+) {
+    let entity = trigger.target();
+    for marker in children.iter_descendants(entity).filter(|child| markers.contains(*child)) {
+        commands.entity(marker).try_despawn();
+    }
+    // This also runs when the enemy is despawned, so none of this may assume it is still around.
+    commands.entity(entity).try_remove::<(Armor, Shield)>();
+}
+
+/// Vampiric elites heal for part of the damage they deal.
+pub fn vampiric_life_steal(
+    trigger : Trigger<Damage>,
+    mut elites : Query<(&Elite, &mut Health), Without<Dying>>,
+) {
+    let Some(source) = trigger.source else { return };
+    if source == trigger.target() { return }
+    let Ok((elite, mut health)) = elites.get_mut(source) else { return };
+    if !elite.has(Affix::Vampiric) { return }
+    health.heal(trigger.amount * VAMPIRIC_LIFE_STEAL);
+}
+
+/// Damages everything with health around an exploding elite, except for other enemies.
+pub fn explode_on_death(
+    trigger : Trigger<EnemyDied>,
+    mut commands : Commands,
+    elites : Query<&Elite>,
+    targets : Query<(Entity, &GlobalTransform), (With<Health>, Without<Enemy>)>,
+) {
+    let Ok(elite) = elites.get(trigger.target()) else { return };
+    if !elite.has(Affix::ExplodesOnDeath) { return }
+
+    for (entity, transform) in targets.iter() {
+        if transform.translation().distance(trigger.position) > EXPLOSION_RADIUS { continue; }
+        commands.trigger_targets(Damage::from_source(EXPLOSION_DAMAGE, trigger.target()), entity);
+    }
+}
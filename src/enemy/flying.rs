@@ -0,0 +1,185 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{enemy::{death::{Dying, EnemyDied}, hit_reaction::Staggered, perception::{pursue, Awareness}, threat::{CurrentTarget, Targetable}, EnemyBehavior, SpecialEnemyBehavior}, lod::{Lod, LodFrame}, spells::damage::Damage, steering::{self, AvoidanceVelocity, LocalAvoidance, SteeringAgent}, util::vec2_vec3, GameState};
+
+const FLYER_OBSTACLE_LOOK_AHEAD: f32 = 3.0;
+const SWOOP_HIT_DISTANCE: f32 = 1.2;
+const SWOOP_MAX_DURATION: f32 = 1.5;
+/// How high above the target's origin a swoop aims, roughly at the chest.
+const SWOOP_AIM_HEIGHT: f32 = 0.5;
+
+//==============================================================================================
+//        Flying Plugin
+//==============================================================================================
+
+/// Everything shared by enemies that fly. Flyers have no Tnua controller, they hold their altitude
+/// and fly straight over the navmesh, only steering around obstacles tall enough to reach them.
+pub struct FlyingPlugin;
+
+impl Plugin for FlyingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_observer(ground_dead_flyers)
+            .add_systems(Update, (flyer_attack, swoop).chain().in_set(SpecialEnemyBehavior))
+            .add_systems(Update, drive_flyers.after(LocalAvoidance).run_if(in_state(GameState::InGame)))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Flying Components
+//==============================================================================================
+
+/// An enemy that flies. It needs a dynamic body without gravity, see [`flying_body`].
+#[derive(Component, Clone, Debug)]
+pub struct Flyer {
+    /// The height above the ground it cruises at.
+    pub altitude : f32,
+    /// How quickly it corrects its height, in units per second per unit it is off.
+    pub climb_rate : f32,
+    pub swoop_speed : f32,
+    pub swoop_damage : f32,
+    /// How close, over the ground, the target has to be before the flyer swoops at it.
+    pub swoop_range : f32,
+    pub swoop_cooldown : Timer,
+}
+
+impl Flyer {
+    pub fn new(altitude : f32, swoop_damage : f32, swoop_cooldown : f32) -> Self {
+        Flyer {
+            altitude,
+            climb_rate : 3.0,
+            swoop_speed : 8.0,
+            swoop_damage,
+            swoop_range : 3.0,
+            swoop_cooldown : Timer::from_seconds(swoop_cooldown, TimerMode::Once),
+        }
+    }
+}
+
+/// A flyer diving at its target. It pulls back up once it hits or the dive takes too long.
+#[derive(Component, Debug)]
+pub struct Swoop {
+    pub target : Entity,
+    timer : Timer,
+}
+
+/// The physics every flyer needs. Rotation is locked since nothing turns it but its facing.
+pub fn flying_body() -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        GravityScale(0.0),
+        LockedAxes::ROTATION_LOCKED,
+        LinearDamping(2.0),
+    )
+}
+
+//==============================================================================================
+//        Flying Systems
+//==============================================================================================
+
+/// Flies over to whatever the flyer is after and swoops at it once it is close enough. Flyers go
+/// straight for beacons like anything else, they don't queue up for a siege slot.
+pub fn flyer_attack(
+    mut commands : Commands,
+    mut flyers : Query<(Entity, &Transform, &mut Flyer, &mut EnemyBehavior, &mut SteeringAgent, &CurrentTarget, Option<&Awareness>, &Lod), (Without<Staggered>, Without<Swoop>)>,
+    targets : Query<(&GlobalTransform, &Targetable)>,
+    spatial_query : SpatialQuery,
+    lod_frame : Res<LodFrame>,
+    time : Res<Time>,
+) {
+    let obstacle_filter = steering::obstacle_filter();
+
+    for (entity, transform, mut flyer, mut behavior, mut agent, target, awareness, lod) in flyers.iter_mut() {
+        flyer.swoop_cooldown.tick(time.delta());
+        if !matches!(behavior.as_ref(), EnemyBehavior::Idle | EnemyBehavior::AttackTarget) { continue }
+        if !lod_frame.should_update(entity, lod) { continue }
+
+        let Some((target_entity, (target_transform, targetable))) = target.entity().and_then(|target| targets.get(target).ok().map(|found| (target, found))) else {
+            *behavior = EnemyBehavior::Idle;
+            agent.stop();
+            continue;
+        };
+
+        let next = if targetable.kind.is_landmark() { EnemyBehavior::AttackTarget } else { pursue(target_entity, awareness) };
+        if !next.is_attack_target() {
+            *behavior = next;
+            continue;
+        }
+        *behavior = EnemyBehavior::AttackTarget;
+
+        let position = transform.translation.xz();
+        let target_position = target_transform.translation().xz();
+        let move_vector = steering::arrive(position, target_position, agent.max_speed, flyer.swoop_range);
+        let avoid_vector = steering::obstacle_avoidance(&spatial_query, transform.translation, move_vector, FLYER_OBSTACLE_LOOK_AHEAD, &obstacle_filter);
+        agent.steer(move_vector + avoid_vector);
+        agent.face(target_position - position);
+
+        if position.distance(target_position) <= flyer.swoop_range && flyer.swoop_cooldown.finished() {
+            flyer.swoop_cooldown.reset();
+            commands.entity(entity).insert(Swoop {
+                target : target_entity,
+                timer : Timer::from_seconds(SWOOP_MAX_DURATION, TimerMode::Once),
+            });
+        }
+    }
+}
+
+pub fn swoop(
+    mut commands : Commands,
+    mut flyers : Query<(Entity, &Transform, &Flyer, &mut Swoop)>,
+    targets : Query<&GlobalTransform>,
+    time : Res<Time>,
+) {
+    for (entity, transform, flyer, mut swoop) in flyers.iter_mut() {
+        swoop.timer.tick(time.delta());
+        let Ok(target) = targets.get(swoop.target) else {
+            commands.entity(entity).remove::<Swoop>();
+            continue;
+        };
+
+        let aim = target.translation() + Vec3::Y * SWOOP_AIM_HEIGHT;
+        if transform.translation.distance(aim) <= SWOOP_HIT_DISTANCE {
+            commands.trigger_targets(Damage::from_source(flyer.swoop_damage, entity), swoop.target);
+            commands.entity(entity).remove::<Swoop>();
+        } else if swoop.timer.finished() {
+            commands.entity(entity).remove::<Swoop>();
+        }
+    }
+}
+
+/// Hands the avoided velocity to the body and holds the altitude. A swooping flyer ignores both
+/// and dives straight at its target.
+pub fn drive_flyers(
+    mut flyers : Query<(&mut Transform, &mut LinearVelocity, &AvoidanceVelocity, &SteeringAgent, &Flyer, Option<&Swoop>), (Without<Staggered>, Without<Dying>)>,
+    targets : Query<&GlobalTransform>,
+) {
+    for (mut transform, mut velocity, avoidance, agent, flyer, swoop) in flyers.iter_mut() {
+        let swoop_target = swoop.and_then(|swoop| targets.get(swoop.target).ok());
+        if let Some(target) = swoop_target {
+            let aim = target.translation() + Vec3::Y * SWOOP_AIM_HEIGHT;
+            velocity.0 = (aim - transform.translation).normalize_or_zero() * flyer.swoop_speed;
+        } else {
+            let climb = (flyer.altitude - transform.translation.y) * flyer.climb_rate;
+            velocity.0 = vec2_vec3(avoidance.0) + Vec3::Y * climb;
+        }
+
+        // Nothing else turns a flyer, so it is pointed where it wants to face by hand.
+        if let Some(forward) = agent.forward(avoidance.0) {
+            transform.look_to(forward, Vec3::Y);
+        }
+    }
+}
+
+/// A dead flyer drops out of the sky.
+pub fn ground_dead_flyers(
+    trigger : Trigger<EnemyDied>,
+    mut commands : Commands,
+    flyers : Query<(), With<Flyer>>,
+) {
+    if !flyers.contains(trigger.target()) { return }
+    commands.entity(trigger.target())
+        .remove::<Swoop>()
+        .insert(GravityScale(1.0));
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use avian3d::prelude::{ComputedMass, LinearVelocity};
+use bevy::prelude::*;
+
+use crate::{enemy::Enemy, spells::damage::Damage, util::{vec2_vec3, AnimationControlerFor}};
+
+//==============================================================================================
+//        HitReaction Plugin
+//==============================================================================================
+
+pub struct HitReactionPlugin;
+
+impl Plugin for HitReactionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<HitFlashMaterials>()
+            .add_observer(react_to_damage)
+            .add_systems(Update, (tick_stagger, tick_hit_flash))
+        ;
+    }
+}
+
+//==============================================================================================
+//        HitReaction Components
+//==============================================================================================
+
+/// How an enemy type reacts to being hit. Every field can be turned off on its own by setting it
+/// to zero or `None`.
+#[derive(Component, Clone, Debug)]
+pub struct HitReaction {
+    /// Played on the enemy's animation player when it takes damage.
+    pub animation : Option<AnimationNodeIndex>,
+    /// The impulse applied away from the source of the damage. Heavier enemies get pushed less.
+    pub knockback : f32,
+    /// How long the enemy stops thinking for after being hit.
+    pub stagger : f32,
+    pub flash_color : Color,
+    pub flash_duration : f32,
+}
+
+impl Default for HitReaction {
+    fn default() -> Self {
+        HitReaction {
+            animation : None,
+            knockback : 0.0,
+            stagger : 0.0,
+            flash_color : Color::WHITE,
+            flash_duration : 0.0,
+        }
+    }
+}
+
+/// While this is on an enemy, its AI is paused and it slides along with whatever knockback it took.
+#[derive(Component)]
+pub struct Staggered(pub Timer);
+
+/// The materials an enemy had before it started flashing, so they can be put back afterwards.
+#[derive(Component)]
+pub struct HitFlash {
+    timer : Timer,
+    originals : Vec<(Entity, Handle<StandardMaterial>)>,
+}
+
+/// One unlit material per flash color, shared between every enemy that flashes that color.
+#[derive(Resource, Default)]
+pub struct HitFlashMaterials(HashMap<[u8; 4], Handle<StandardMaterial>>);
+
+impl HitFlashMaterials {
+    pub fn get_or_create(&mut self, color : Color, materials : &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+        self.0.entry(color.to_srgba().to_u8_array())
+            .or_insert_with(|| materials.add(StandardMaterial {
+                base_color : color,
+                emissive : color.to_linear() * 4.0,
+                unlit : true,
+                ..default()
+            }))
+            .clone()
+    }
+}
+
+//==============================================================================================
+//        HitReaction Systems
+//==============================================================================================
+
+pub fn react_to_damage(
+    trigger : Trigger<Damage>,
+    mut commands : Commands,
+    mut enemies : Query<(&GlobalTransform, &HitReaction, &mut LinearVelocity, Option<&ComputedMass>, Option<&AnimationControlerFor>, Option<&HitFlash>), With<Enemy>>,
+    sources : Query<&GlobalTransform>,
+    mut animation_players : Query<&mut AnimationPlayer>,
+    children : Query<&Children>,
+    meshes : Query<&MeshMaterial3d<StandardMaterial>>,
+    mut flash_materials : ResMut<HitFlashMaterials>,
+    mut materials : ResMut<Assets<StandardMaterial>>,
+) {
+    let entity = trigger.target();
+    let Ok((transform, reaction, mut velocity, mass, animated_models, flashing)) = enemies.get_mut(entity) else { return };
+
+    if reaction.knockback > 0.0 {
+        let source = trigger.source.and_then(|source| sources.get(source).ok());
+        if let Some(source) = source {
+            let direction = (transform.translation().xz() - source.translation().xz()).normalize_or_zero();
+            let inverse_mass = mass.map(|mass| mass.inverse()).unwrap_or(1.0);
+            velocity.0 += vec2_vec3(direction) * reaction.knockback * inverse_mass;
+        }
+    }
+
+    if reaction.stagger > 0.0 {
+        commands.entity(entity).insert(Staggered(Timer::from_seconds(reaction.stagger, TimerMode::Once)));
+    }
+
+    if let (Some(animation), Some(animated_models)) = (reaction.animation, animated_models) {
+        for model in animated_models.iter() {
+            let Ok(mut player) = animation_players.get_mut(model) else { continue };
+            player.start(animation);
+        }
+    }
+
+    // An enemy that is already flashing keeps its original materials, it just flashes for longer.
+    if reaction.flash_duration > 0.0 {
+        let timer = Timer::from_seconds(reaction.flash_duration, TimerMode::Once);
+        if let Some(flashing) = flashing {
+            commands.entity(entity).insert(HitFlash { timer, originals: flashing.originals.clone() });
+        } else {
+            let flash = flash_materials.get_or_create(reaction.flash_color, &mut materials);
+            let originals = children.iter_descendants(entity)
+                .filter_map(|child| meshes.get(child).ok().map(|material| (child, material.0.clone())))
+                .collect::<Vec<_>>();
+            for (child, _) in originals.iter() {
+                commands.entity(*child).insert(MeshMaterial3d(flash.clone()));
+            }
+            commands.entity(entity).insert(HitFlash { timer, originals });
+        }
+    }
+}
+
+pub fn tick_stagger(
+    mut commands : Commands,
+    mut staggered : Query<(Entity, &mut Staggered)>,
+    time : Res<Time>,
+) {
+    for (entity, mut stagger) in staggered.iter_mut() {
+        stagger.0.tick(time.delta());
+        if stagger.0.finished() {
+            commands.entity(entity).remove::<Staggered>();
+        }
+    }
+}
+
+pub fn tick_hit_flash(
+    mut commands : Commands,
+    mut flashing : Query<(Entity, &mut HitFlash)>,
+    time : Res<Time>,
+) {
+    for (entity, mut flash) in flashing.iter_mut() {
+        flash.timer.tick(time.delta());
+        if !flash.timer.finished() { continue; }
+
+        for (child, material) in flash.originals.drain(..) {
+            if let Ok(mut child) = commands.get_entity(child) {
+                child.insert(MeshMaterial3d(material));
+            }
+        }
+        commands.entity(entity).remove::<HitFlash>();
+    }
+}
@@ -1,21 +1,41 @@
-use std::f64::consts::PI;
+use std::f32::consts::PI;
 
 use avian3d::prelude::*;
 use bevy::{animation::AnimationTarget, prelude::*};
 use bevy_tnua::{controller, prelude::*, TnuaNotPlatform};
 use bevy_tnua_avian3d::TnuaAvian3dSensorShape;
-use crate::{arena::beacon::BeaconQuery, assets::{EnemyAnimationGraphs, EnemyAssets, WizardAssets}, character::PlayerCharacter, enemy::{Enemy, EnemySpawnAnimationComplete, EnemyType, SpawnEnemy, SpecialEnemyBehavior}, util::{AnimatedModelFor, AnimatedSceneCreated, GameCollisionLayer, Health, SceneRootWithAnimation}};
+use crate::{arena::beacon::Beacon, assets::{EnemyAnimationGraphs, EnemyAssets}, enemy::{death::{DeathSequence, Dying, LastHitBy}, elite::Elite, hit_reaction::{HitReaction, Staggered}, squad::Routed, siege::{Besieging, SiegeSlots, SIEGE_QUEUE_RADIUS, SIEGE_SLOT_TOLERANCE}, orders::{Returning, StandingOrders}, perception::{pursue, Awareness, Perception}, threat::{CurrentTarget, TargetKind, TargetPriorities}, Enemy, EnemySpawnAnimationComplete, EnemyType, SpawnEnemy, SpecialEnemyBehavior}, pool::{activate, EntityPool, EntityPoolPlugin, Pooled}, lod::{Lod, LodFrame}, spells::damage::Damage, steering::{self, SteeringAgent}, util::{AnimatedModelFor, AnimatedSceneCreated, AnimationControlerFor, GameCollisionLayer, Health, SceneRootWithAnimation}};
 
 use super::EnemyBehavior;
 
 const MINION_HEIGHT: f32 = 1.0;
+const MINION_RADIUS: f32 = 0.5;
 const MINION_HEALTH: f32 = 5.0;
 const MINION_SPEED: f32 = 3.0;
-const MINION_AGRO_RANGE: f32 = 5.0;
+const MINION_SIGHT_RANGE: f32 = 8.0;
+const MINION_FIELD_OF_VIEW: f32 = 2.1;
+const MINION_AWARENESS_RADIUS: f32 = 2.5;
+const MINION_MEMORY: f32 = 4.0;
 const MINION_ATTACK_COOLDOWN: f32 = 2.0;
 const MINION_ATTACK_RANGE: f32 = 1.5;
-const MINION_BEACON_ATTACK_RANGE: f32 = 5.0;
 const MINION_BEACON_DPS : f32 = 5.0;
+const MINION_OBSTACLE_LOOK_AHEAD : f32 = 2.0;
+const MINION_KNOCKBACK : f32 = 4.0;
+const MINION_STAGGER : f32 = 0.4;
+const MINION_HIT_FLASH : f32 = 0.12;
+const MINION_CORPSE_DURATION : f32 = 4.0;
+const MINION_PLAYER_PRIORITY : f32 = 3.0;
+const MINION_TURRET_PRIORITY : f32 = 2.0;
+const MINION_BEACON_PRIORITY : f32 = 1.0;
+const MINION_WALKER : GroundWalker = GroundWalker {
+    height : MINION_HEIGHT,
+    radius : MINION_RADIUS,
+    speed : MINION_SPEED,
+    knockback : MINION_KNOCKBACK,
+    stagger : MINION_STAGGER,
+    hit_flash : MINION_HIT_FLASH,
+    corpse_duration : MINION_CORPSE_DURATION,
+};
 
 //==============================================================================================
 //        Minion Plugin
@@ -26,8 +46,10 @@ pub struct MinionPlugin;
 impl Plugin for MinionPlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_plugins(EntityPoolPlugin::<Minion>::default())
+            
             .add_observer(spawn_minion_enemy)
-            .add_systems(Update, (minion_goto, minion_attack_player, minion_idle, manage_minion_animation).chain().in_set(SpecialEnemyBehavior))
+            .add_systems(Update, (minion_goto, minion_attack_target, minion_idle, minion_attack_beacon, manage_minion_animation::<Minion>).chain().in_set(SpecialEnemyBehavior))
         ;
     }
 }
@@ -67,62 +89,150 @@ pub fn minion_stabbed(
 //        Spawn a minion Enemy
 //==============================================================================================
 
+/// An enemy walking around on the skeleton minion model. The minion, the bomber and the
+/// shieldbearer only differ in these numbers and in what they carry.
+#[derive(Clone, Copy, Debug)]
+pub struct GroundWalker {
+    pub height : f32,
+    pub radius : f32,
+    pub speed : f32,
+    pub knockback : f32,
+    pub stagger : f32,
+    pub hit_flash : f32,
+    pub corpse_duration : f32,
+}
+
+impl GroundWalker {
+    /// The part of the walker that has to be put back onto a pooled enemy when it is reused.
+    pub fn living(self, position : Vec3, enemy_animation_graphs : &EnemyAnimationGraphs) -> impl Bundle {
+        (
+            Transform::from_translation(Vec3::new(position.x, self.height, position.z)),
+            Enemy {
+                height_from_ground : self.height,
+                speed : self.speed,
+            },
+            EnemyBehavior::Spawning,
+            CollisionLayers::new(GameCollisionLayer::Enemy, [
+                GameCollisionLayer::Player,
+                GameCollisionLayer::Default,
+                GameCollisionLayer::Spell,
+                GameCollisionLayer::Obstacle,
+            ]),
+            RigidBody::Dynamic,
+            TnuaController::default(),
+            SteeringAgent::new(self.radius, self.speed),
+            HitReaction {
+                animation : Some(enemy_animation_graphs.minion_hit),
+                knockback : self.knockback,
+                stagger : self.stagger,
+                flash_color : Color::WHITE,
+                flash_duration : self.hit_flash,
+            },
+        )
+    }
+
+    /// The collider, the death and the model, which stay on the enemy through every life.
+    pub fn body(self, enemy_assets : &EnemyAssets, enemy_animation_graphs : &EnemyAnimationGraphs) -> impl Bundle {
+        (
+            Collider::capsule(self.radius, 0.5),
+            TnuaAvian3dSensorShape(Collider::cylinder(self.radius - 0.01, 0.0)),
+            TnuaNotPlatform,
+            DeathSequence {
+                animation : Some(enemy_animation_graphs.minion_death),
+                collapse : false,
+                despawn_delay : self.corpse_duration,
+            },
+            SceneRootWithAnimation::new(enemy_assets.skeleton_minion.clone())
+                .with_animation_graph(enemy_animation_graphs.minion_graph.clone())
+                .with_animation(enemy_animation_graphs.minion_spawn)
+                .with_transform(Transform::from_translation((0.0, -self.height, 0.0).into()).with_rotation(Quat::from_rotation_y(PI))),
+        )
+    }
+}
+
+/// Everything a minion needs to be alive. This is put back onto pooled minions when they are reused.
+fn living_minion(position : Vec3, enemy_animation_graphs : &EnemyAnimationGraphs) -> impl Bundle {
+    (
+        MINION_WALKER.living(position, enemy_animation_graphs),
+        (Minion::default(), EnemyType::Minion),
+        Health::new(MINION_HEALTH),
+        TargetPriorities::none()
+            .with_priority(TargetKind::Player, MINION_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Summon, MINION_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Turret, MINION_TURRET_PRIORITY, None)
+            .with_priority(TargetKind::Beacon, MINION_BEACON_PRIORITY, None),
+        Perception::new(MINION_SIGHT_RANGE, MINION_FIELD_OF_VIEW, MINION_MEMORY)
+            .with_awareness_radius(MINION_AWARENESS_RADIUS),
+    )
+}
+
 pub fn spawn_minion_enemy(
     trigger : Trigger<SpawnEnemy>,
     mut commands : Commands,
     enemy_assets : Res<EnemyAssets>,
-    enemy_animation_graphs : Res<EnemyAnimationGraphs>
+    enemy_animation_graphs : Res<EnemyAnimationGraphs>,
+    mut pool : ResMut<EntityPool<Minion>>,
+    animated_models : Query<&AnimationControlerFor>,
+    mut animation_players : Query<&mut AnimationPlayer>,
 ) {
-    if trigger.1 != EnemyType::Minion { return }
-    let position = trigger.0;
+    if trigger.enemy_type != EnemyType::Minion { return }
+    let position = trigger.position;
+    
+    if let Some(entity) = pool.take() {
+        // Whatever the last life left behind has to go before the elite affixes are rolled on again.
+        commands.entity(entity)
+            .remove::<(Dying, LastHitBy, Elite, StandingOrders, Returning, Routed)>()
+            .insert(living_minion(position, &enemy_animation_graphs));
+        activate(&mut commands, entity);
+        
+        for model in animated_models.get(entity).into_iter().flat_map(|models| models.iter()) {
+            let Ok(mut player) = animation_players.get_mut(model) else { continue };
+            player.stop_all();
+            player.play(enemy_animation_graphs.minion_spawn);
+        }
+        
+        trigger.insert_extras(&mut commands.entity(entity));
+        return;
+    }
     
-    commands.spawn((
+    let mut minion = commands.spawn((
         Name::new("Minion"),
-        Transform::from_translation(Vec3::new(position.x, MINION_HEIGHT, position.z)),
-        Minion::default(),
-        Enemy {
-            height_from_ground: MINION_HEIGHT,
-            speed: MINION_SPEED,
-        },
-        Health::new(MINION_HEALTH),
-        EnemyBehavior::Spawning,
-        CollisionLayers::new(GameCollisionLayer::Enemy, [
-            GameCollisionLayer::Player,
-            GameCollisionLayer::Default, 
-            GameCollisionLayer::Spell,
-        ]),
-        RigidBody::Dynamic,
-        Collider::capsule(0.5, 0.5),
-        TnuaController::default(),
-        TnuaAvian3dSensorShape(Collider::cylinder(0.49, 0.0)),
-        TnuaNotPlatform,
-        SceneRootWithAnimation::new(enemy_assets.skeleton_minion.clone())
-            .with_animation_graph(enemy_animation_graphs.minion_graph.clone())
-            .with_animation(enemy_animation_graphs.minion_spawn)
-            .with_transform(Transform::from_translation((0.0, -1.0, 0.0).into()).with_rotation(Quat::from_rotation_y(PI as f32))),
-    )).observe(on_minion_scene_added);
+        living_minion(position, &enemy_animation_graphs),
+        Pooled,
+        MINION_WALKER.body(&enemy_assets, &enemy_animation_graphs),
+    ));
+    minion.observe(on_minion_scene_added).observe(arm_minion);
+    
+    trigger.insert_extras(&mut minion);
 }
 
+/// Lets a [`GroundWalker`] go about its business once it has climbed out of the ground.
 pub fn on_minion_scene_added(
     trigger : Trigger<AnimatedSceneCreated>,
     mut commands : Commands,
-    spawner : Res<SceneSpawner>,
-    rig : Query<(Entity, &Name), With<AnimationTarget>>,
-    assets : Res<EnemyAssets>
 ) {
-    let controler = trigger.target().clone();
+    let controler = trigger.target();
     commands.entity(trigger.0).observe(move |_ : Trigger<EnemySpawnAnimationComplete>, mut enemies : Query<&mut EnemyBehavior, With<Enemy>>| {
         let Ok(mut enemy_behavior) = enemies.get_mut(controler) else { return; };
         *enemy_behavior = EnemyBehavior::Idle;
     });
-    
+}
+
+/// Puts a blade in the right hand of the model.
+pub fn arm_minion(
+    trigger : Trigger<AnimatedSceneCreated>,
+    mut commands : Commands,
+    spawner : Res<SceneSpawner>,
+    rig : Query<(Entity, &Name), With<AnimationTarget>>,
+    assets : Res<EnemyAssets>
+) {
     let hand = spawner.iter_instance_entities(trigger.1)
         .filter_map(|e| rig.get(e).map(|part| Some(part)).unwrap_or(None))
         .find(|part| part.1.as_str() == "handslot.r")
     ;
     
     if let Some(hand) = hand {
-        let transform = Transform::from_rotation(Quat::from_rotation_y(PI as f32));
+        let transform = Transform::from_rotation(Quat::from_rotation_y(PI));
         commands.entity(hand.0).insert(
             SceneRootWithAnimation::new(assets.skeleton_blade.clone())
                 .with_transform(transform)
@@ -134,14 +244,42 @@ pub fn on_minion_scene_added(
 //        Animating the Minion
 //==============================================================================================
 
-fn manage_minion_animation(
-    minions : Query<(&LinearVelocity, &EnemyBehavior), With<Minion>>,
+/// The run clips of an enemy that is animated like a minion, see [`manage_minion_animation`].
+pub trait MinionRunAnimations : Component {
+    /// Played on the legs, or on the whole body when there is no upper body clip.
+    fn run(animations : &EnemyAnimationGraphs) -> AnimationNodeIndex;
+    /// Played on the upper body while it isn't busy stabbing or reacting to a hit.
+    fn run_upper_body(animations : &EnemyAnimationGraphs) -> Option<AnimationNodeIndex>;
+}
+
+impl MinionRunAnimations for Minion {
+    fn run(animations : &EnemyAnimationGraphs) -> AnimationNodeIndex {
+        animations.minion_run_bottom
+    }
+
+    fn run_upper_body(animations : &EnemyAnimationGraphs) -> Option<AnimationNodeIndex> {
+        Some(animations.minion_run_top)
+    }
+}
+
+/// Keeps [`manage_minion_animation`] off of an enemy while it plays its spellcast.
+#[derive(Component)]
+pub struct Casting;
+
+pub fn manage_minion_animation<T : MinionRunAnimations>(
+    minions : Query<(&LinearVelocity, &EnemyBehavior, &Lod), (With<T>, Without<Casting>)>,
     mut minion_animated_models : Query<(&AnimatedModelFor, &mut AnimationPlayer)>,
     animations : Res<EnemyAnimationGraphs>,
+    lod_frame : Res<LodFrame>,
 ) {
+    let run = T::run(&animations);
+    let run_upper_body = T::run_upper_body(&animations);
+    
     for (animated_model_for, mut animation_player) in minion_animated_models.iter_mut() {
-        let Ok((minion_velocity, minion_behavior)) = minions.get(animated_model_for.0) else { continue; };
-        if matches!(minion_behavior, EnemyBehavior::Spawning) { continue; }
+        let Ok((minion_velocity, minion_behavior, lod)) = minions.get(animated_model_for.0) else { continue; };
+        if lod.tier.freezes_animation() || !lod_frame.should_update(animated_model_for.0, lod) { continue; }
+        if matches!(minion_behavior, EnemyBehavior::Spawning | EnemyBehavior::AttackBeacon) { continue; }
+        animation_player.stop(animations.minion_spellcast);
         
         let velocity_magnitude = minion_velocity.length();
         let is_stab_finished = animation_player.animation(animations.minion_stab).map(|animation| animation.is_finished()).unwrap_or(false);
@@ -149,16 +287,28 @@ fn manage_minion_animation(
             animation_player.stop(animations.minion_stab);
         }
         
+        let is_hit_finished = animation_player.animation(animations.minion_hit).map(|animation| animation.is_finished()).unwrap_or(false);
+        if is_hit_finished {
+            animation_player.stop(animations.minion_hit);
+        }
+        let upper_body_busy = animation_player.is_playing_animation(animations.minion_stab) || animation_player.is_playing_animation(animations.minion_hit);
+        
         if velocity_magnitude >= 0.05 {
-            animation_player.play(animations.minion_run_bottom).repeat();
+            animation_player.play(run).repeat();
             
             animation_player.stop(animations.minion_idle);
-            if !animation_player.is_playing_animation(animations.minion_stab) {
-                animation_player.play(animations.minion_run_top).repeat();
+            if let Some(run_upper_body) = run_upper_body {
+                if upper_body_busy {
+                    animation_player.stop(run_upper_body);
+                } else {
+                    animation_player.play(run_upper_body).repeat();
+                }
             }
         } else {
-            animation_player.stop(animations.minion_run_bottom);
-            animation_player.stop(animations.minion_run_top);
+            animation_player.stop(run);
+            if let Some(run_upper_body) = run_upper_body {
+                animation_player.stop(run_upper_body);
+            }
             animation_player.play(animations.minion_idle).repeat();
         }
     }
@@ -170,72 +320,60 @@ fn manage_minion_animation(
 //==============================================================================================
 
 pub fn minion_goto (
-    player : Single<&Transform, With<PlayerCharacter>>,
-    mut minions : Query<&mut EnemyBehavior, With<Minion>>,
-    spacial_query: SpatialQuery,
+    mut commands : Commands,
+    mut minions : Query<(Entity, &mut EnemyBehavior, &CurrentTarget, Option<&Awareness>), (With<Minion>, Without<Staggered>)>,
 ) {
-    let entities = spacial_query.shape_intersections(
-        &Collider::sphere(MINION_AGRO_RANGE),
-        player.translation, 
-        Quat::default(),                 // Shape rotation
-        &SpatialQueryFilter::default()
-    );
-    
-    for entity in entities.iter() {
-        let Ok(mut behavior) = minions.get_mut(*entity) else { continue; };
-        if !behavior.is_goto() {continue;}
-        *behavior = EnemyBehavior::AttackPlayer;
+    for (entity, mut behavior, target, awareness) in minions.iter_mut() {
+        if !(behavior.is_goto() || behavior.is_approach()) {continue;}
+        let Some(target_entity) = target.entity().filter(|_| !target.is(TargetKind::Beacon)) else {continue;};
+        *behavior = pursue(target_entity, awareness);
+        commands.entity(entity).remove::<Besieging>();
     }
 }
 
 //==============================================================================================
-//        Minion Attack Player
+//        Minion Attack Target
 //==============================================================================================
 
-pub fn minion_attack_player(
-    mut commmands : Commands,
-    player : Single<&Transform, With<PlayerCharacter>>,
-    player_assets : Res<WizardAssets>,
-    mut minions : Query<(Entity, &mut EnemyBehavior, &mut TnuaController, &Transform, &mut Minion)>,
+pub fn minion_attack_target(
+    mut minions : Query<(Entity, &mut EnemyBehavior, &mut SteeringAgent, &Transform, &mut Minion, &CurrentTarget, Option<&Awareness>), Without<Staggered>>,
+    targets : Query<&GlobalTransform>,
     mut minion_animators : Query<(&AnimatedModelFor, &mut AnimationPlayer)>,
     enemy_animation_graphs : Res<EnemyAnimationGraphs>,
     spacial_query : SpatialQuery,
     time : Res<Time>
 ) {
-    let enemies_within_agro_range = spacial_query.shape_intersections(
-        &Collider::sphere(MINION_AGRO_RANGE),
-        player.translation, 
-        Quat::default(),                 // Shape rotation
-        &SpatialQueryFilter::default()
-    );
+    let obstacle_filter = steering::obstacle_filter();
     
-    let enemies_within_attack_range = spacial_query.shape_intersections(
-        &Collider::sphere(MINION_ATTACK_RANGE),
-        player.translation, 
-        Quat::default(),                 // Shape rotation
-        &SpatialQueryFilter::default()
-    );
-    
-    for (entity, mut behavior, mut controller, transform, mut minion) in minions.iter_mut() {
-        if !behavior.is_attack_player() {continue;}
-        
-        let direction_to_player = (player.translation.xz() - transform.translation.xz()).normalize_or_zero();
-        let move_vector = direction_to_player * MINION_SPEED;
+    for (entity, mut behavior, mut agent, transform, mut minion, target, awareness) in minions.iter_mut() {
+        if !behavior.is_attack_target() {continue;}
         
-        controller.basis(TnuaBuiltinWalk {
-            desired_velocity: (move_vector.x, 0.0, move_vector.y).into(),
-            float_height: MINION_HEIGHT,
-            desired_forward: Some(Dir3::from_xyz_unchecked(direction_to_player.x, 0.0, direction_to_player.y)),
-            ..default()
-        });
-        
-        if !enemies_within_agro_range.contains(&entity) {
+        let Some(target_entity) = target.entity().filter(|_| !target.is(TargetKind::Beacon)) else {
             *behavior = EnemyBehavior::Idle;
+            continue;
+        };
+        // Lost sight of it, go looking for it where it was last seen.
+        let next = pursue(target_entity, awareness);
+        if !next.is_attack_target() {
+            *behavior = next;
+            continue;
         }
+        let Ok(target) = targets.get(target_entity) else {
+            *behavior = EnemyBehavior::Idle;
+            continue;
+        };
+        let target_position = target.translation().xz();
+        
+        let position = transform.translation.xz();
+        let move_vector = steering::arrive(position, target_position, agent.max_speed, MINION_ATTACK_RANGE);
+        let avoid_vector = steering::obstacle_avoidance(&spacial_query, transform.translation, move_vector, MINION_OBSTACLE_LOOK_AHEAD, &obstacle_filter);
+        
+        agent.steer(move_vector + avoid_vector);
+        agent.face(target_position - position);
         
         minion.attack_cooldown.tick(time.delta());
         
-        if enemies_within_attack_range.contains(&entity) && minion.attack_cooldown.just_finished() {
+        if position.distance(target_position) <= MINION_ATTACK_RANGE + MINION_RADIUS && minion.attack_cooldown.just_finished() {
             let Some((_, mut animation_player)) = minion_animators.iter_mut().find(|i| i.0.0 == entity) else { continue };
             animation_player.stop(enemy_animation_graphs.minion_run_top);
             animation_player.start(enemy_animation_graphs.minion_stab);
@@ -249,33 +387,51 @@ pub fn minion_attack_player(
 //==============================================================================================
 
 pub fn minion_idle(
-    mut enemy : Query<(Entity,&mut TnuaController, &mut EnemyBehavior, &Enemy, &LinearVelocity, &Transform)>,
-    player : Single<&Transform, With<PlayerCharacter>>,
-    spacial_query : SpatialQuery,
-    beacon : BeaconQuery,
+    mut commands : Commands,
+    mut enemy : Query<(Entity, &mut SteeringAgent, &mut EnemyBehavior, &Transform, Option<&Besieging>, &CurrentTarget, Option<&Awareness>, &Lod), (With<Minion>, Without<Staggered>)>,
+    lod_frame : Res<LodFrame>,
+    sieges : Query<(&Transform, &SiegeSlots), With<Beacon>>,
 ) {
-    for (entity, mut controller, mut behavior, enemy, rb, transform) in enemy.iter_mut() {
-        if !(matches!(behavior.as_ref(), &EnemyBehavior::Idle)) { return }
-        controller.basis(TnuaBuiltinWalk {
-            desired_velocity: (0.0, 0.0, 0.0).into(),
-            float_height: enemy.height_from_ground,
-            ..default()
-        });
+    for (entity, mut agent, mut behavior, transform, besieging, target, awareness, lod) in enemy.iter_mut() {
+        if !(matches!(behavior.as_ref(), &EnemyBehavior::Idle)) { continue }
+        if !lod_frame.should_update(entity, lod) { continue }
+        agent.stop();
         
-        let enemies_within_agro_range = spacial_query.shape_intersections(
-            &Collider::sphere(MINION_AGRO_RANGE),
-            player.translation, 
-            Quat::default(),                 // Shape rotation
-            &SpatialQueryFilter::default()
-        );
+        let Some(target_entity) = target.entity() else { continue };
+        if !target.is(TargetKind::Beacon) {
+            *behavior = pursue(target_entity, awareness);
+            commands.entity(entity).remove::<Besieging>();
+            continue;
+        }
         
-        if enemies_within_agro_range.contains(&entity) {
-            *behavior = EnemyBehavior::AttackPlayer;
-        } else if beacon.within_range(transform, MINION_BEACON_ATTACK_RANGE) {
-            println!("WITHIN RANGE");
-            *behavior = EnemyBehavior::AttackBeacon;
-        } else {
-            *behavior = EnemyBehavior::goto(beacon.closest_point(transform, MINION_BEACON_ATTACK_RANGE))
+        let Some(besieging) = besieging.filter(|besieging| besieging.beacon == target_entity) else {
+            // Not besieging this beacon yet, so join its queue and walk over to it.
+            commands.entity(entity).insert(Besieging::new(target_entity));
+            *behavior = EnemyBehavior::approach(target_entity, SIEGE_QUEUE_RADIUS);
+            continue;
+        };
+        
+        let Ok((beacon_transform, siege)) = sieges.get(besieging.beacon) else {
+            commands.entity(entity).remove::<Besieging>();
+            continue;
+        };
+        let beacon_position = beacon_transform.translation.xz();
+        
+        match besieging.slot {
+            Some(slot) => {
+                let slot_position = beacon_position + siege.slot_offset(slot);
+                if transform.translation.xz().distance(slot_position) <= SIEGE_SLOT_TOLERANCE {
+                    *behavior = EnemyBehavior::AttackBeacon;
+                } else {
+                    *behavior = EnemyBehavior::goto(slot_position);
+                }
+            }
+            None => {
+                // Still waiting for a slot, hang around the queue ring until one frees up.
+                if transform.translation.xz().distance(beacon_position) > siege.queue_radius + SIEGE_SLOT_TOLERANCE {
+                    *behavior = EnemyBehavior::approach(besieging.beacon, siege.queue_radius);
+                }
+            }
         }
     }
 }
@@ -285,29 +441,35 @@ pub fn minion_idle(
 //===============================================================================================
 
 pub fn minion_attack_beacon(
-    mut minions : Query<(&mut EnemyBehavior, &mut TnuaController, &Transform), With<Minion>>,
+    mut commands : Commands,
+    mut minions : Query<(&mut EnemyBehavior, &mut SteeringAgent, &Transform, Option<&Besieging>, &CurrentTarget), (With<Minion>, Without<Staggered>)>,
     mut animators : Query<(&AnimatedModelFor, &mut AnimationPlayer)>,
-    mut beacon : BeaconQuery,
+    beacons : Query<&Transform, With<Beacon>>,
     enemy_animations : Res<EnemyAnimationGraphs>,
     time : Res<Time>
 ) {
     for (model_for, mut player) in animators.iter_mut() {
-        let Ok((behavior, mut controller, transform)) = minions.get_mut(model_for.0) else { continue };
+        let Ok((mut behavior, mut agent, transform, besieging, target)) = minions.get_mut(model_for.0) else { continue };
         if !behavior.is_atack_beacon() { continue; }
-
-        println!("Attacking Beacon");
+        
+        // Only enemies holding a slot are allowed to attack, anyone else goes back to deciding what to do.
+        let Some((beacon_entity, beacon_transform)) = besieging
+            .filter(|besieging| besieging.slot.is_some() && target.entity() == Some(besieging.beacon))
+            .and_then(|besieging| beacons.get(besieging.beacon).ok().map(|transform| (besieging.beacon, transform)))
+        else {
+            player.stop(enemy_animations.minion_spellcast);
+            *behavior = EnemyBehavior::Idle;
+            continue;
+        };
 
         player.play(enemy_animations.minion_spellcast).repeat();
+        player.stop(enemy_animations.minion_idle);
         player.stop(enemy_animations.minion_run_top);
         player.stop(enemy_animations.minion_run_bottom);
 
-        controller.basis(TnuaBuiltinWalk {
-            desired_velocity: (0.0, 0.0, 0.0).into(),
-            float_height: MINION_HEIGHT,
-            desired_forward: Some(beacon.towards_beacon(transform)),
-            ..default()
-        });
+        agent.stop();
+        agent.face(beacon_transform.translation.xz() - transform.translation.xz());
 
-        beacon.take_damage(MINION_BEACON_DPS * time.delta_secs());
+        commands.trigger_targets(Damage::from_source(MINION_BEACON_DPS * time.delta_secs(), model_for.0), beacon_entity);
     }   
-}
\ No newline at end of file
+}
@@ -1,21 +1,49 @@
-use avian3d::prelude::{Collider, SpatialQuery, SpatialQueryFilter};
-use bevy::{color::palettes, ecs::entity, gizmos, platform::collections::HashMap, prelude::*};
+use std::f32::consts::TAU;
+
+use avian3d::prelude::{Collider, SpatialQuery};
+use bevy::{color::palettes, ecs::entity, gizmos, prelude::*};
+use bevy_spatial::SpatialAccess;
 use bevy_tnua::prelude::*;
 use rand::Rng;
 use strum::{EnumCount, FromRepr};
 use vleue_navigator::{prelude::*, Path};
 use weighted_rand::{builder::{NewBuilder, WalkerTableBuilder}, table::WalkerTable};
 
-use crate::{arena::NavmeshQuery, enemy::minion::MinionPlugin, util::{vec2_vec3, Health}};
+use crate::{arena::{flow_field::{FlowFieldGrid, FlowFieldTarget, FlowFields}, NavmeshQuery, SpawnZone}, character::PlayerCharacter, enemy::{bomber::BomberPlugin, boss::{debug_spawn_boss, BossPlugin}, death::DeathPlugin, elite::{Elite, ElitePlugin}, flying::FlyingPlugin, hit_reaction::{HitReactionPlugin, Staggered}, minion::MinionPlugin, orders::{Orders, OrdersPlugin, StandingOrders}, pathing::PathingPlugin, perception::PerceptionPlugin, shieldbearer::ShieldbearerPlugin, siege::SiegePlugin, squad::{Formation, Squad, SquadMember, SquadPlugin}, threat::ThreatPlugin, wisp::WispPlugin}, steering::{self, AvoidanceVelocity, LocalAvoidance, SteeringAgent, SteeringTree}, lod::{Lod, LodFrame}, util::vec2_vec3, GameState};
 
+pub mod bomber;
+pub mod boss;
+pub mod death;
+pub mod elite;
+pub mod flying;
+pub mod hit_reaction;
 pub mod minion;
+pub mod orders;
+pub mod pathing;
+pub mod perception;
+pub mod shieldbearer;
+pub mod siege;
+pub mod squad;
+pub mod threat;
+pub mod wisp;
 
 const MAX_ENEMIES: u32 = 1000;
-const SPAWN_RADIUS: i32 = 7;
+const DEFAULT_SPAWN_RADIUS: f32 = 7.0;
+const SPAWN_ATTEMPTS: usize = 20;
+/// How many frames enemies that found no free point are kept around before they are given up on.
+const SPAWN_RETRY_FRAMES: u32 = 120;
+const MIN_SPAWN_SPACING: f32 = 1.0;
+const MIN_SPAWN_DISTANCE_FROM_PLAYER: f32 = 5.0;
+const SPAWN_CLEARANCE: f32 = 0.6;
+const SPAWN_CLEARANCE_HEIGHT: f32 = 4.0;
+/// Enemies sit above the ground in the steering tree, this makes sure spacing checks still reach them.
+const ENEMY_TREE_HEIGHT_SLACK: f32 = 3.0;
 const MAX_ENEMIES_PER_SPAWN: u32 = 20;
 const ENEMY_CROWDING_SPACE : f32 = 5.0;
+const ENEMY_NEIGHBOUR_RADIUS : f32 = 2.0;
 const ENEMY_SEPORATION_FACTOR : f32 = 0.9;
 const ENENY_CORNER_CUTTING : f32 = 0.5;
+const ENEMY_STAGGER_ACCELERATION : f32 = 8.0;
 
 //==============================================================================================
 //        Enemy Plguin
@@ -39,17 +67,33 @@ impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugins(MinionPlugin)
+            .add_plugins(SiegePlugin)
+            .add_plugins(PathingPlugin)
+            .add_plugins(HitReactionPlugin)
+            .add_plugins(DeathPlugin)
+            .add_plugins(ElitePlugin)
+            .add_plugins(BossPlugin)
+            .add_plugins(FlyingPlugin)
+            .add_plugins(WispPlugin)
+            .add_plugins(BomberPlugin)
+            .add_plugins(ShieldbearerPlugin)
+            .add_plugins(ThreatPlugin)
+            .add_plugins(PerceptionPlugin)
+            .add_plugins(OrdersPlugin)
+            .add_plugins(SquadPlugin)
             
             .init_resource::<EnemyCount>()
+            .init_resource::<PendingSpawns>()
             
             .add_observer(spawn_enemies)
         
             .add_systems(Update, (enemy_idle_and_spawning, enemy_goto).chain().in_set(DefaultEnemyBehavior))
-            .add_systems(PostUpdate, check_for_dead_enemies)
+            .add_systems(Update, place_pending_spawns.before(DefaultEnemyBehavior).run_if(in_state(GameState::InGame)))
+            .add_systems(Update, drive_enemy_controllers.after(LocalAvoidance).run_if(in_state(GameState::InGame)))
         ;
         
         if self.0 {
-            app.add_systems(Update, debug_goto.in_set(DefaultEnemyBehavior));
+            app.add_systems(Update, (debug_goto.in_set(DefaultEnemyBehavior), debug_spawn_boss.run_if(in_state(GameState::InGame))));
         }
     }
 }
@@ -59,11 +103,19 @@ impl Plugin for EnemyPlugin {
 //==============================================================================================
 
 #[derive(Component)]
+#[require(EnemySteering, Lod)]
 pub struct Enemy {
     pub height_from_ground : f32,
     pub speed : f32,
 }
 
+/// The last velocities the enemy steered with, kept around for debugging.
+#[derive(Component, Default)]
+pub struct EnemySteering {
+    pub separation : Vec2,
+    pub desired : Vec2,
+}
+
 //============================================================================================== 
 //        Enemy General Stuff
 //==============================================================================================
@@ -71,11 +123,21 @@ pub struct Enemy {
 #[derive(Resource, Clone, Debug, Default)]
 pub struct EnemyCount(u32);
 
+impl EnemyCount {
+    pub fn count(&self) -> u32 {
+        self.0
+    }
+}
+
 #[repr(usize)]
-#[derive(FromRepr, Hash, PartialEq, Eq, PartialOrd, Ord, EnumCount)]
+#[derive(Component, FromRepr, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, EnumCount)]
 pub enum EnemyType {
     Minion,
     Mage,
+    SkeletonMageBoss,
+    Wisp,
+    Bomber,
+    Shieldbearer,
 }
 
 //==============================================================================================
@@ -93,10 +155,18 @@ pub enum EnemyBehavior {
     #[default]
     Idle,
     Spawning,
+    /// Standing at its post, see [`Orders::Guard`].
     Guard,
     Goto(Vec2, Option<Path>, usize),
+    /// Follow the flow field of a [`FlowFieldTarget`] until within range of it.
+    Approach(Entity, f32),
     AttackBeacon,
-    AttackPlayer,
+    /// Chase down and hit the [`CurrentTarget`](threat::CurrentTarget) of the enemy.
+    AttackTarget,
+    /// Look for the [`CurrentTarget`](threat::CurrentTarget) around where it was last seen or heard.
+    Search(Vec2),
+    /// Keep to a slot of the [`Squad`] formation while the leader moves.
+    InFormation,
 }
 
 impl EnemyBehavior {    
@@ -104,12 +174,24 @@ impl EnemyBehavior {
         EnemyBehavior::Goto(position, None, 0)
     }
     
+    pub fn approach(target : Entity, range : f32) -> Self {
+        EnemyBehavior::Approach(target, range)
+    }
+    
     pub fn is_goto(&self) -> bool {
         matches!(self, EnemyBehavior::Goto(..))
     }
     
-    pub fn is_attack_player(&self) -> bool {
-        matches!(self, EnemyBehavior::AttackPlayer)
+    pub fn is_approach(&self) -> bool {
+        matches!(self, EnemyBehavior::Approach(..))
+    }
+    
+    pub fn is_search(&self) -> bool {
+        matches!(self, EnemyBehavior::Search(_))
+    }
+    
+    pub fn is_attack_target(&self) -> bool {
+        matches!(self, EnemyBehavior::AttackTarget)
     }
 
     pub fn is_atack_beacon(&self) -> bool {
@@ -124,27 +206,65 @@ impl EnemyBehavior {
 #[derive(Event, Clone)]
 pub struct EnemySpawnAnimationComplete;
 
+/// Where a group of enemies should be spawned.
+#[derive(Clone, Debug)]
+pub enum SpawnLocation {
+    Point(Vec3),
+    /// The name of a [`SpawnZone`] in the arena.
+    Zone(String),
+}
+
 #[derive(Event)]
-pub struct SpawnEnemiesEvent(Vec3, u32, WalkerTable);
+pub struct SpawnEnemiesEvent {
+    location : SpawnLocation,
+    radius : Option<f32>,
+    number_of_enemies : u32,
+    table : WalkerTable,
+    elite_chance : f32,
+    orders : Option<Orders>,
+    squad : Option<Formation>,
+}
 
 impl SpawnEnemiesEvent {
     pub fn collect(&self) -> Vec<EnemyType> {
-        (0..self.1).filter_map(|_| EnemyType::from_repr(self.2.next()) ).collect()
+        (0..self.number_of_enemies).filter_map(|_| EnemyType::from_repr(self.table.next()) ).collect()
+    }
+    
+    /// Rolls whether the next enemy out of this event is an elite.
+    pub fn roll_elite(&self) -> Option<Elite> {
+        rand::rng().random_bool(self.elite_chance.clamp(0.0, 1.0) as f64).then(Elite::roll)
     }
 }
 
 pub struct SpawnEnemiesEventBuilder {
-    position: Vec3,
+    location: SpawnLocation,
+    radius: Option<f32>,
     number_of_enemies: u32,
-    weights : [u32; EnemyType::COUNT]
+    weights : [u32; EnemyType::COUNT],
+    elite_chance : f32,
+    orders : Option<Orders>,
+    squad : Option<Formation>,
 }
 
 impl SpawnEnemiesEventBuilder {
     pub fn new(position: Vec3) -> Self {
+        Self::at(SpawnLocation::Point(position))
+    }
+    
+    /// Spawns the enemies inside of the [`SpawnZone`] with this name.
+    pub fn in_zone(name: impl Into<String>) -> Self {
+        Self::at(SpawnLocation::Zone(name.into()))
+    }
+    
+    fn at(location: SpawnLocation) -> Self {
         Self {
-            position,
+            location,
+            radius : None,
             number_of_enemies : 1,
-            weights : [0; EnemyType::COUNT]
+            weights : [0; EnemyType::COUNT],
+            elite_chance : 0.0,
+            orders : None,
+            squad : None,
         }
     }
     
@@ -158,42 +278,185 @@ impl SpawnEnemiesEventBuilder {
         self
     }
     
+    /// The chance, from 0 to 1, that each enemy spawns as an elite with random affixes.
+    pub fn with_elite_chance(mut self, elite_chance : f32) -> Self {
+        self.elite_chance = elite_chance;
+        self
+    }
+    
+    /// What the enemies do while they have nothing to attack. Without orders they go for the beacons.
+    pub fn with_orders(mut self, orders : Orders) -> Self {
+        self.orders = Some(orders);
+        self
+    }
+    
+    /// Groups the enemies into a squad that follows its leader in this formation.
+    pub fn with_squad(mut self, formation : Formation) -> Self {
+        self.squad = Some(formation);
+        self
+    }
+    
+    /// How far from the spawn location enemies may be placed. Overrides the radius of a zone.
+    pub fn with_radius(mut self, radius : f32) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+    
     pub fn build(self) -> SpawnEnemiesEvent {
-        SpawnEnemiesEvent(self.position, self.number_of_enemies, WalkerTableBuilder::new(&self.weights).build())
+        SpawnEnemiesEvent {
+            location : self.location,
+            radius : self.radius,
+            number_of_enemies : self.number_of_enemies,
+            table : WalkerTableBuilder::new(&self.weights).build(),
+            elite_chance : self.elite_chance,
+            orders : self.orders,
+            squad : self.squad,
+        }
     }
 }
 
+/// Triggered once for every enemy that has been placed, the observer for its type spawns it.
 #[derive(Event)]
-pub struct SpawnEnemy(Vec3, EnemyType);
+pub struct SpawnEnemy {
+    pub position : Vec3,
+    pub enemy_type : EnemyType,
+    pub elite : Option<Elite>,
+    pub orders : Option<Orders>,
+    pub squad : Option<Entity>,
+}
+
+impl SpawnEnemy {
+    /// Puts the affixes, orders and squad the spawn asked for onto the enemy that was spawned for it.
+    pub fn insert_extras(&self, enemy : &mut EntityCommands) {
+        if let Some(elite) = self.elite.clone() {
+            enemy.insert(elite);
+        }
+        if let Some(orders) = self.orders.clone() {
+            enemy.insert(StandingOrders::new(orders, self.position.xz()));
+        }
+        if let Some(squad) = self.squad {
+            enemy.insert(SquadMember(squad));
+        }
+    }
+}
+
+/// A spawn waiting to be placed on the navmesh. The enemies are rolled as soon as the spawn is
+/// asked for, they are only placed once there is a built navmesh to place them on.
+pub struct PendingSpawn {
+    location : SpawnLocation,
+    radius : Option<f32>,
+    enemies : Vec<(EnemyType, Option<Elite>)>,
+    orders : Option<Orders>,
+    squad : Option<Formation>,
+    /// The squad the enemies placed so far joined, the rest join it once they are placed.
+    squad_entity : Option<Entity>,
+    /// Frames in which some of the enemies found no free point.
+    retries : u32,
+}
+
+#[derive(Resource, Default)]
+pub struct PendingSpawns(Vec<PendingSpawn>);
 
 pub fn spawn_enemies(
     trigger : Trigger<SpawnEnemiesEvent>,
-    mut commands : Commands,
-    mut enemy_count: ResMut<EnemyCount>
+    mut pending : ResMut<PendingSpawns>,
 ) {
-    let mut rng = rand::rng();
-    for enemy in trigger.collect().into_iter() {
-        let rand_vec = Vec3::new(rng.random_range(-SPAWN_RADIUS..=SPAWN_RADIUS) as f32, 0.0, rng.random_range(-SPAWN_RADIUS..=SPAWN_RADIUS) as f32);
-        if enemy_count.0 > MAX_ENEMIES { return }
-        commands.trigger(SpawnEnemy(trigger.0 + rand_vec, enemy));
-        enemy_count.0 += 1;
-    }
+    pending.0.push(PendingSpawn {
+        location : trigger.location.clone(),
+        radius : trigger.radius,
+        enemies : trigger.collect().into_iter().map(|enemy| (enemy, trigger.roll_elite())).collect(),
+        orders : trigger.orders.clone(),
+        squad : trigger.squad,
+        squad_entity : None,
+        retries : 0,
+    });
 }
 
-//==============================================================================================
-//        Enemy Systems
-//==============================================================================================
-
-pub fn check_for_dead_enemies (
+/// Places every pending spawn on a random walkable point within its radius. Points inside of
+/// obstacles, too close to the player or too close to another enemy are thrown away. Enemies over
+/// the cap or without a free point stay pending and are tried again on a later frame.
+pub fn place_pending_spawns(
     mut commands : Commands,
-    enemies : Query<(Entity, &Health), With<Enemy>>,
-    mut enemy_count : ResMut<EnemyCount>
+    mut pending : ResMut<PendingSpawns>,
+    mut enemy_count : ResMut<EnemyCount>,
+    navmesh : NavmeshQuery,
+    zones : Query<(&SpawnZone, &Transform)>,
+    player : Query<&Transform, With<PlayerCharacter>>,
+    steering_tree : Res<SteeringTree>,
+    spatial_query : SpatialQuery,
 ) {
-    for (entity, enemy) in enemies.iter() {
-        if enemy.current_health <= 0.0 {
-            enemy_count.0 -= 1;
-            commands.entity(entity).despawn();
+    if pending.0.is_empty() || !navmesh.is_built() { return }
+    
+    let mut rng = rand::rng();
+    let player_position = player.single().ok().map(|transform| transform.translation.xz());
+    let obstacle_filter = steering::obstacle_filter();
+    let clearance = Collider::cylinder(SPAWN_CLEARANCE, SPAWN_CLEARANCE_HEIGHT);
+    let mut placed : Vec<Vec2> = Vec::new();
+    
+    for mut spawn in std::mem::take(&mut pending.0) {
+        let (center, zone_radius) = match &spawn.location {
+            SpawnLocation::Point(position) => (position.xz(), DEFAULT_SPAWN_RADIUS),
+            SpawnLocation::Zone(name) => {
+                let Some((zone, transform)) = zones.iter().find(|(zone, _)| zone.name == *name) else {
+                    warn!("There is no spawn zone called {name}");
+                    continue;
+                };
+                (transform.translation.xz(), zone.radius)
+            }
+        };
+        let radius = spawn.radius.unwrap_or(zone_radius);
+        
+        let is_valid = |point : Vec2, placed : &[Vec2]| {
+            navmesh.is_walkable(point)
+                && player_position.is_none_or(|player| player.distance(point) >= MIN_SPAWN_DISTANCE_FROM_PLAYER)
+                && placed.iter().all(|other| other.distance(point) >= MIN_SPAWN_SPACING)
+                && steering_tree.within_distance(vec2_vec3(point), MIN_SPAWN_SPACING + ENEMY_TREE_HEIGHT_SLACK)
+                    .iter()
+                    .all(|(other, _)| other.xz().distance(point) >= MIN_SPAWN_SPACING)
+                && spatial_query.shape_intersections(&clearance, vec2_vec3(point), Quat::IDENTITY, &obstacle_filter).is_empty()
+        };
+        
+        let mut unplaced = Vec::new();
+        let mut blocked = false;
+        for (enemy_type, elite) in std::mem::take(&mut spawn.enemies) {
+            if enemy_count.0 >= MAX_ENEMIES {
+                unplaced.push((enemy_type, elite));
+                continue;
+            }
+            
+            let point = (0..SPAWN_ATTEMPTS)
+                .map(|_| center + Vec2::from_angle(rng.random_range(0.0..TAU)) * radius * rng.random::<f32>().sqrt())
+                .find(|point| is_valid(*point, &placed));
+            let Some(point) = point else {
+                unplaced.push((enemy_type, elite));
+                blocked = true;
+                continue;
+            };
+            
+            if spawn.squad_entity.is_none() {
+                spawn.squad_entity = spawn.squad.map(|formation| commands.spawn((Name::new("Squad"), Squad::new(formation))).id());
+            }
+            placed.push(point);
+            commands.trigger(SpawnEnemy {
+                position : vec2_vec3(point),
+                enemy_type,
+                elite,
+                orders : spawn.orders.clone(),
+                squad : spawn.squad_entity,
+            });
+            enemy_count.0 += 1;
+        }
+        
+        if unplaced.is_empty() { continue }
+        if blocked {
+            spawn.retries += 1;
+        }
+        if spawn.retries > SPAWN_RETRY_FRAMES {
+            warn!("Gave up on placing {} enemies at {:?}, there was no free point", unplaced.len(), spawn.location);
+            continue;
         }
+        spawn.enemies = unplaced;
+        pending.0.push(spawn);
     }
 }
 
@@ -202,100 +465,86 @@ pub fn check_for_dead_enemies (
 //==============================================================================================
 
 pub fn enemy_goto(
-    mut enemies : Query<(Entity, &Transform, &mut TnuaController, &mut EnemyBehavior, &Enemy)>,
-    navmesh : NavmeshQuery,
-    spacial_query: SpatialQuery,
-    mut gizmos : Gizmos,
+    mut enemies : Query<(Entity, &Transform, &mut SteeringAgent, &mut EnemyBehavior, &mut EnemySteering, &Enemy, &Lod), Without<Staggered>>,
+    targets : Query<&Transform, (With<FlowFieldTarget>, Without<Enemy>)>,
+    steering_tree : Res<SteeringTree>,
+    flow_field_grid : Res<FlowFieldGrid>,
+    flow_fields : Res<FlowFields>,
+    lod_frame : Res<LodFrame>,
 ) {
-    //Get all enemies that are in the goto behavior
-    let mut enemies = enemies.iter_mut()
-        .filter_map(|(entity, transform, controller, behavior, enemy)| {
-            if !behavior.is_goto() { return None };
-            Some((entity, (transform, controller, behavior, enemy)))
-        })
-        .collect::<HashMap<_, _>>()
-    ;
-
-    let intended_velocities = enemies.iter().map(|(entity, (transform, _, behavior, enemy))| {
-        let nearby_enmemies = spacial_query.shape_intersections(
-            &Collider::sphere(2.0), 
-            transform.translation, 
-            Quat::default(), 
-            &SpatialQueryFilter::default(), 
-        );
-
-        let nearby_enemies =  nearby_enmemies.iter().filter_map(|e| {
-            if e == entity { return None }
-            enemies.get(e).map(|i| Some(i.0)).unwrap_or(None)
-        });
-
-        let mut avoiding = 0;
-        let mut speration_velocity = Vec2::default();
+    enemies.par_iter_mut().for_each(|(entity, transform, mut agent, mut behavior, mut steering, enemy, lod)| {
+        if !(behavior.is_goto() || behavior.is_approach()) { return };
+        if !lod_frame.should_update(entity, lod) { return };
+        
         let current_location = transform.translation.xz();
         
-        for other in nearby_enemies {
-            let other_location = other.translation.xz();
-            let distance = current_location.distance(other_location);
-            if distance < ENEMY_CROWDING_SPACE {
-                let direction_away = (current_location - other_location).normalize_or_zero();
-                let weighted_velocity = direction_away / distance;
-                speration_velocity += weighted_velocity;
-                avoiding += 1;
+        let neighbours = steering_tree.within_distance(transform.translation, ENEMY_NEIGHBOUR_RADIUS)
+            .into_iter()
+            .filter(|(_, other)| *other != Some(entity))
+            .map(|(other_location, _)| other_location.xz());
+        let speration_velocity = steering::separation(current_location, neighbours, ENEMY_CROWDING_SPACE) * ENEMY_SEPORATION_FACTOR;
+
+        let desired_target = match behavior.as_ref() {
+            EnemyBehavior::Goto(destination, Some(path), index) => {
+                Some(*path.path.get(*index).unwrap_or(destination))
             }
-        }
-
-        if avoiding > 0 {
-            speration_velocity /= avoiding as f32;
-            speration_velocity *= ENEMY_SEPORATION_FACTOR;
-        }
-
-        let EnemyBehavior::Goto(destination, Some(path), index) = behavior.as_ref() else { return (entity.clone(), (speration_velocity, speration_velocity.normalize_or_zero()))};
-        let current_node = path.path.get(*index).unwrap_or(destination);
+            EnemyBehavior::Approach(target, _) => {
+                targets.get(*target).ok().map(|target_transform| {
+                    // The goal cell has no direction of its own, so close to the target just walk straight at it.
+                    flow_fields.direction(&flow_field_grid, *target, current_location)
+                        .filter(|direction| *direction != Vec2::ZERO)
+                        .map(|direction| current_location + direction)
+                        .unwrap_or(target_transform.translation.xz())
+                })
+            }
+            _ => None,
+        };
 
-        let desired_velocity = (current_node - current_location).normalize_or_zero() * enemy.speed;
+        let desired_velocity = desired_target
+            .map(|target| steering::seek(current_location, target, enemy.speed))
+            .unwrap_or_default();
         
-        let indended_velocity = speration_velocity + desired_velocity;
-        gizmos.ray(transform.translation + enemy.height_from_ground, vec2_vec3(speration_velocity), bevy::color::palettes::tailwind::BLUE_500);
-        gizmos.ray(transform.translation + enemy.height_from_ground, vec2_vec3(desired_velocity), bevy::color::palettes::tailwind::RED_500);
-        gizmos.ray(transform.translation + enemy.height_from_ground, vec2_vec3(indended_velocity), bevy::color::palettes::tailwind::VIOLET_500);
-        (entity.clone(), (indended_velocity, indended_velocity.normalize_or_zero()))
-    }).collect::<HashMap<Entity, _>>();
-    
-    for (entity, (intended_velocity, intended_direction)) in intended_velocities.into_iter() {
-        let Some((transform, controller, behavior, enemy)) = enemies.get_mut(&entity) else { unreachable!() };
-        let direction = if intended_direction.length_squared() == 0.0 {None} else {Some(Dir3::from_xyz_unchecked(intended_direction.x, 0.0, intended_direction.y))};
-        controller.basis(TnuaBuiltinWalk {
-            desired_velocity: (intended_velocity.x, 0.0, intended_velocity.y).into(),
-            float_height: enemy.height_from_ground,
-            desired_forward: direction,
-            ..default()
-        });
+        steering.separation = speration_velocity;
+        steering.desired = desired_velocity;
+        agent.steer(speration_velocity + desired_velocity);
+
+        if let EnemyBehavior::Approach(target, range) = behavior.as_ref() {
+            let arrived = targets.get(*target)
+                .map(|target_transform| current_location.distance(target_transform.translation.xz()) <= *range)
+                .unwrap_or(true);
+            if arrived { *behavior = EnemyBehavior::Idle }
+            return;
+        }
 
         let should_be_idle = {
-            let EnemyBehavior::Goto(destination, path, index) = behavior.as_mut() else { continue };
-            if path.is_none() {
-                *path = navmesh.path_from_tranform(&transform, *destination);
-                if path.is_none() { continue; }
-            }
-            let path = path.as_ref().unwrap();
+            let EnemyBehavior::Goto(destination, path, index) = behavior.as_mut() else { return };
+            // The path is requested through the path request queue, wait for it to come back.
+            let Some(path) = path.as_ref() else { return };
             let current_node = path.path.get(*index).unwrap_or(destination);
     
-            if transform.translation.xz().distance(*current_node) < ENENY_CORNER_CUTTING {
+            if current_location.distance(*current_node) < ENENY_CORNER_CUTTING {
                 *index += 1
             }
     
             *index >= path.path.len()
         };
 
-        if should_be_idle { **behavior = EnemyBehavior::Idle }
-    }
+        if should_be_idle { *behavior = EnemyBehavior::Idle }
+    });
 }
 
 pub fn debug_goto (
-    enemy_behaviors : Query<(&EnemyBehavior, &Transform)>,
+    enemy_behaviors : Query<(&EnemyBehavior, &Transform, &EnemySteering, &Enemy)>,
     mut gizmos : Gizmos
 ) {
-    for (enemy_behavior, transform) in enemy_behaviors.iter() {
+    for (enemy_behavior, transform, steering, enemy) in enemy_behaviors.iter() {
+        if enemy_behavior.is_goto() || enemy_behavior.is_approach() {
+            let origin = transform.translation + enemy.height_from_ground;
+            gizmos.ray(origin, vec2_vec3(steering.separation), bevy::color::palettes::tailwind::BLUE_500);
+            gizmos.ray(origin, vec2_vec3(steering.desired), bevy::color::palettes::tailwind::RED_500);
+            gizmos.ray(origin, vec2_vec3(steering.separation + steering.desired), bevy::color::palettes::tailwind::VIOLET_500);
+        }
+        
         if let EnemyBehavior::Goto(destination, Some(path), index) = enemy_behavior {
             let mut points : Vec<Vec3> = vec![(transform.translation.x, 0.1, transform.translation.z).into()];
             path.path.iter().skip(*index).for_each(|point| points.push(Vec3::new(point.x, 0.1, point.y)));
@@ -314,13 +563,42 @@ pub fn debug_goto (
 //==============================================================================================
 
 pub fn enemy_idle_and_spawning(
-    mut enemy : Query<(&mut TnuaController, &EnemyBehavior, &Enemy)>,
+    mut enemy : Query<(&mut SteeringAgent, &EnemyBehavior), With<Enemy>>,
+) {
+    for (mut agent, behavior) in enemy.iter_mut() {
+        if !(matches!(behavior, &EnemyBehavior::Idle | &EnemyBehavior::Spawning)) {continue;}
+        agent.stop();
+    }
+}
+
+//==============================================================================================
+//        Enemy Controllers
+//==============================================================================================
+
+/// Behaviours only set where an enemy would like to go, this hands the avoided velocity to Tnua.
+pub fn drive_enemy_controllers(
+    mut enemies : Query<(Entity, &mut TnuaController, &SteeringAgent, &AvoidanceVelocity, &Enemy, &Lod, Has<Staggered>)>,
+    lod_frame : Res<LodFrame>,
 ) {
-    for (mut controller, behavior, enemy) in enemy.iter_mut() {
-        if !(matches!(behavior, &EnemyBehavior::Idle | &EnemyBehavior::Spawning)) {return;}
+    for (entity, mut controller, agent, velocity, enemy, lod, staggered) in enemies.iter_mut() {
+        // The basis sticks around in the controller, so skipping a frame keeps the last one going.
+        if !staggered && !lod_frame.should_update(entity, lod) { continue; }
+        
+        if staggered {
+            // Barely hold the enemy back so it slides with the knockback instead of stopping dead.
+            controller.basis(TnuaBuiltinWalk {
+                desired_velocity: Vec3::ZERO,
+                float_height: enemy.height_from_ground,
+                acceleration: ENEMY_STAGGER_ACCELERATION,
+                ..default()
+            });
+            continue;
+        }
+        
         controller.basis(TnuaBuiltinWalk {
-            desired_velocity: (0.0, 0.0, 0.0).into(),
+            desired_velocity: vec2_vec3(velocity.0),
             float_height: enemy.height_from_ground,
+            desired_forward: agent.forward(velocity.0),
             ..default()
         });
     }
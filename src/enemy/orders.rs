@@ -0,0 +1,170 @@
+use std::{collections::HashMap, f32::consts::TAU};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{arena::{NavmeshQuery, PatrolWaypoint}, enemy::{hit_reaction::Staggered, threat::{select_targets, CurrentTarget, ThreatTable}, DefaultEnemyBehavior, EnemyBehavior}, lod::{Lod, LodFrame}, steering::SteeringAgent};
+
+const GUARD_POST_TOLERANCE: f32 = 0.75;
+const WANDER_ATTEMPTS: usize = 10;
+const WANDER_MIN_PAUSE: f32 = 1.0;
+const WANDER_MAX_PAUSE: f32 = 3.0;
+
+//==============================================================================================
+//        Orders Plugin
+//==============================================================================================
+
+pub struct OrdersPlugin;
+
+impl Plugin for OrdersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (enforce_leash.before(select_targets), follow_standing_orders.after(select_targets)).in_set(DefaultEnemyBehavior))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Orders
+//==============================================================================================
+
+/// What an enemy does while it has nothing to attack. Enemies with orders leave the beacons alone
+/// and only fight what they notice.
+#[derive(Clone, Debug)]
+pub enum Orders {
+    /// Hold the spot the enemy spawned on. Anything further than `leash` from it is given up on.
+    Guard { leash : f32 },
+    /// Walk the [`PatrolWaypoint`]s of the route with this name, over and over.
+    Patrol(String),
+    /// Walk between random reachable points within `radius` of where the enemy spawned.
+    Wander { radius : f32 },
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct StandingOrders {
+    pub orders : Orders,
+    /// Where the enemy was spawned. Guards hold it and wanderers stay around it.
+    pub post : Vec2,
+    next_waypoint : Option<usize>,
+    pause : Timer,
+}
+
+impl StandingOrders {
+    pub fn new(orders : Orders, post : Vec2) -> Self {
+        StandingOrders {
+            orders,
+            post,
+            next_waypoint : None,
+            pause : Timer::from_seconds(0.0, TimerMode::Once),
+        }
+    }
+
+    pub fn leash(&self) -> Option<f32> {
+        match self.orders {
+            Orders::Guard { leash } => Some(leash),
+            _ => None,
+        }
+    }
+}
+
+/// A guard that went past its leash and is walking back to its post. It ignores every target
+/// until it gets there.
+#[derive(Component)]
+pub struct Returning;
+
+//==============================================================================================
+//        Orders Systems
+//==============================================================================================
+
+pub fn enforce_leash(
+    mut commands : Commands,
+    mut enemies : Query<(Entity, &Transform, &StandingOrders, &mut EnemyBehavior, &mut CurrentTarget, &mut ThreatTable, Has<Returning>)>,
+) {
+    for (entity, transform, orders, mut behavior, mut target, mut threat, returning) in enemies.iter_mut() {
+        if returning {
+            if transform.translation.xz().distance(orders.post) <= GUARD_POST_TOLERANCE || matches!(behavior.as_ref(), EnemyBehavior::Idle) {
+                commands.entity(entity).remove::<Returning>();
+            }
+            continue;
+        }
+
+        let Some(leash) = orders.leash() else { continue };
+        if transform.translation.xz().distance(orders.post) <= leash { continue }
+        if !(behavior.is_attack_target() || behavior.is_search()) { continue }
+
+        target.0 = None;
+        threat.clear();
+        *behavior = EnemyBehavior::goto(orders.post);
+        commands.entity(entity).insert(Returning);
+    }
+}
+
+/// Gives idle enemies with nothing to attack something to do.
+pub fn follow_standing_orders(
+    mut enemies : Query<(Entity, &Transform, &mut StandingOrders, &mut EnemyBehavior, &mut SteeringAgent, &CurrentTarget, &Lod), (Without<Staggered>, Without<Returning>)>,
+    waypoints : Query<(&PatrolWaypoint, &Transform)>,
+    navmesh : NavmeshQuery,
+    lod_frame : Res<LodFrame>,
+    time : Res<Time>,
+) {
+    let mut routes : HashMap<&str, Vec<(u32, Vec2)>> = HashMap::new();
+    for (waypoint, transform) in waypoints.iter() {
+        routes.entry(waypoint.route.as_str()).or_default().push((waypoint.order, transform.translation.xz()));
+    }
+    for route in routes.values_mut() {
+        route.sort_by_key(|(order, _)| *order);
+    }
+
+    let mut rng = rand::rng();
+    for (entity, transform, mut orders, mut behavior, mut agent, target, lod) in enemies.iter_mut() {
+        if !matches!(behavior.as_ref(), EnemyBehavior::Idle | EnemyBehavior::Guard) { continue }
+        if !lod_frame.should_update(entity, lod) { continue }
+
+        // Something to attack, the enemy's own behaviour takes it from here.
+        if target.entity().is_some() {
+            if matches!(behavior.as_ref(), EnemyBehavior::Guard) { *behavior = EnemyBehavior::Idle }
+            continue;
+        }
+
+        let position = transform.translation.xz();
+        match orders.orders.clone() {
+            Orders::Guard { .. } => {
+                agent.stop();
+                if position.distance(orders.post) > GUARD_POST_TOLERANCE {
+                    *behavior = EnemyBehavior::goto(orders.post);
+                } else {
+                    *behavior = EnemyBehavior::Guard;
+                }
+            }
+            Orders::Patrol(route) => {
+                let Some(route) = routes.get(route.as_str()).filter(|route| !route.is_empty()) else {
+                    warn_once!("There is no patrol route called {route}");
+                    continue;
+                };
+                // Join the route at the closest waypoint, after that just keep going around it.
+                let index = orders.next_waypoint.unwrap_or_else(|| {
+                    route.iter().enumerate()
+                        .min_by(|(_, (_, a)), (_, (_, b))| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+                        .map(|(index, _)| index)
+                        .unwrap_or(0)
+                }) % route.len();
+                orders.next_waypoint = Some((index + 1) % route.len());
+                *behavior = EnemyBehavior::goto(route[index].1);
+            }
+            Orders::Wander { radius } => {
+                agent.stop();
+                orders.pause.tick(time.delta() * lod.tier.update_interval());
+                if !orders.pause.finished() { continue }
+
+                let post = orders.post;
+                let point = (0..WANDER_ATTEMPTS)
+                    .map(|_| post + Vec2::from_angle(rng.random_range(0.0..TAU)) * radius * rng.random::<f32>().sqrt())
+                    .find(|point| navmesh.is_walkable(*point));
+                if let Some(point) = point {
+                    *behavior = EnemyBehavior::goto(point);
+                }
+                orders.pause = Timer::from_seconds(rng.random_range(WANDER_MIN_PAUSE..=WANDER_MAX_PAUSE), TimerMode::Once);
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::{prelude::*, tasks::{block_on, poll_once, AsyncComputeTaskPool, Task}};
+use vleue_navigator::Path;
+
+use crate::{arena::{NavMeshRebuilt, NavmeshQuery}, enemy::{enemy_goto, DefaultEnemyBehavior, Enemy, EnemyBehavior}};
+
+const PATH_REQUESTS_PER_FRAME: usize = 8;
+
+//==============================================================================================
+//        Pathing Plugin
+//==============================================================================================
+
+pub struct PathingPlugin;
+
+impl Plugin for PathingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PathRequests>()
+            .add_systems(Update, (
+                    invalidate_paths_on_rebuild,
+                    request_missing_paths,
+                    cancel_stale_path_tasks,
+                    dispatch_path_requests,
+                    receive_path_results,
+                )
+                .chain()
+                .in_set(DefaultEnemyBehavior)
+                .before(enemy_goto)
+            )
+        ;
+    }
+}
+
+//==============================================================================================
+//        Path Requests
+//==============================================================================================
+
+/// Every path an enemy needs goes through here. Requests are handed to the async compute pool a
+/// few at a time, so a whole wave spawning at once doesn't pathfind in a single frame.
+#[derive(Resource, Default)]
+pub struct PathRequests {
+    order : VecDeque<Entity>,
+    pending : HashMap<Entity, PathRequest>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PathRequest {
+    pub from : Vec2,
+    pub to : Vec2,
+}
+
+impl PathRequests {
+    /// Asks for a path for an entity. A newer request from the same entity replaces the older one.
+    pub fn request(&mut self, entity : Entity, from : Vec2, to : Vec2) {
+        if self.pending.insert(entity, PathRequest { from, to }).is_none() {
+            self.order.push_back(entity);
+        }
+    }
+
+    pub fn is_pending(&self, entity : Entity) -> bool {
+        self.pending.contains_key(&entity)
+    }
+
+    pub fn cancel(&mut self, entity : Entity) {
+        self.pending.remove(&entity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn pop(&mut self) -> Option<(Entity, PathRequest)> {
+        while let Some(entity) = self.order.pop_front() {
+            // Cancelled requests leave their entity behind in the order, skip over those.
+            if let Some(request) = self.pending.remove(&entity) {
+                return Some((entity, request));
+            }
+        }
+        None
+    }
+}
+
+/// A path that is being computed on the async compute pool. Removing this component, or despawning
+/// the entity, drops the task and cancels the search.
+#[derive(Component)]
+pub struct PathTask {
+    pub destination : Vec2,
+    task : Task<Option<(Vec2, Path)>>,
+}
+
+//==============================================================================================
+//        Path Request Systems
+//==============================================================================================
+
+/// Re-requests the path of every enemy that is walking through something that isn't walkable
+/// anymore. They keep following the old path until the new one comes back.
+pub fn invalidate_paths_on_rebuild(
+    mut rebuilt : EventReader<NavMeshRebuilt>,
+    mut requests : ResMut<PathRequests>,
+    enemies : Query<(Entity, &Transform, &EnemyBehavior), With<Enemy>>,
+    navmesh : NavmeshQuery,
+) {
+    if rebuilt.is_empty() { return }
+    rebuilt.clear();
+
+    for (entity, transform, behavior) in enemies.iter() {
+        let EnemyBehavior::Goto(destination, Some(path), index) = behavior else { continue };
+
+        let mut remaining = vec![transform.translation.xz()];
+        remaining.extend(path.path.iter().skip(*index));
+        if remaining.last() != Some(destination) {
+            remaining.push(*destination);
+        }
+
+        if !navmesh.is_path_clear(&remaining) {
+            requests.request(entity, transform.translation.xz(), *destination);
+        }
+    }
+}
+
+pub fn request_missing_paths(
+    mut requests : ResMut<PathRequests>,
+    enemies : Query<(Entity, &Transform, &EnemyBehavior), (With<Enemy>, Without<PathTask>)>,
+) {
+    for (entity, transform, behavior) in enemies.iter() {
+        let EnemyBehavior::Goto(destination, None, _) = behavior else { continue };
+        if requests.pending.get(&entity).is_some_and(|request| request.to == *destination) { continue; }
+        requests.request(entity, transform.translation.xz(), *destination);
+    }
+}
+
+/// Drops the tasks of enemies that stopped going where they asked a path for.
+pub fn cancel_stale_path_tasks(
+    mut commands : Commands,
+    mut requests : ResMut<PathRequests>,
+    enemies : Query<(Entity, &EnemyBehavior, Option<&PathTask>), Changed<EnemyBehavior>>,
+) {
+    for (entity, behavior, task) in enemies.iter() {
+        let destination = match behavior {
+            EnemyBehavior::Goto(destination, ..) => Some(*destination),
+            _ => None,
+        };
+
+        if requests.pending.get(&entity).is_some_and(|request| Some(request.to) != destination) {
+            requests.cancel(entity);
+        }
+
+        if task.is_some_and(|task| Some(task.destination) != destination) {
+            commands.entity(entity).remove::<PathTask>();
+        }
+    }
+}
+
+pub fn dispatch_path_requests(
+    mut commands : Commands,
+    mut requests : ResMut<PathRequests>,
+    enemies : Query<(), With<Enemy>>,
+    navmesh : NavmeshQuery,
+) {
+    let Some(navmesh) = navmesh.navmesh() else { return };
+    let task_pool = AsyncComputeTaskPool::get();
+    let mut budget = PATH_REQUESTS_PER_FRAME;
+
+    while budget > 0 {
+        let Some((entity, request)) = requests.pop() else { break };
+        if !enemies.contains(entity) { continue; }
+        budget -= 1;
+
+        let mesh = navmesh.get();
+        let task = task_pool.spawn(async move {
+            if let Some(path) = mesh.path(request.from, request.to) {
+                return Some((request.to, path));
+            }
+            // The destination can't be reached, so head for the closest point to it instead.
+            let closest = mesh.get_closest_point(request.to)?.position();
+            mesh.path(request.from, closest).map(|path| (closest, path))
+        });
+
+        commands.entity(entity).insert(PathTask { destination: request.to, task });
+    }
+}
+
+/// Hands finished paths back to the enemies that asked for them. Enemies that can't get anywhere
+/// near their destination go back to idle so their behaviour can pick something else to do.
+pub fn receive_path_results(
+    mut commands : Commands,
+    mut enemies : Query<(Entity, &mut PathTask, &mut EnemyBehavior), With<Enemy>>,
+) {
+    for (entity, mut path_task, mut behavior) in enemies.iter_mut() {
+        let Some(result) = block_on(poll_once(&mut path_task.task)) else { continue };
+        commands.entity(entity).remove::<PathTask>();
+
+        let EnemyBehavior::Goto(destination, ..) = behavior.as_ref() else { continue };
+        if *destination != path_task.destination { continue; }
+
+        *behavior = match result {
+            Some((destination, path)) => EnemyBehavior::Goto(destination, Some(path), 0),
+            None => EnemyBehavior::Idle,
+        };
+    }
+}
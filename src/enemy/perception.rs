@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{enemy::{death::Dying, hit_reaction::Staggered, threat::{select_targets, CurrentTarget, Targetable}, DefaultEnemyBehavior, EnemyBehavior}, lod::{Lod, LodFrame}, pool::Inactive, spells::Caster, steering::{self, SteeringAgent}, util::Health};
+
+const SEARCH_ARRIVE_DISTANCE: f32 = 1.0;
+const SEARCH_LOOK_AROUND_SPEED: f32 = 2.0;
+const SEARCH_OBSTACLE_LOOK_AHEAD: f32 = 2.0;
+const DEFAULT_EYE_HEIGHT: f32 = 0.5;
+
+//==============================================================================================
+//        Perception Plugin
+//==============================================================================================
+
+pub struct PerceptionPlugin;
+
+impl Plugin for PerceptionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Noise>()
+            .add_systems(Update, (look_for_targets, hear_noises, forget_targets).chain().in_set(DefaultEnemyBehavior).before(select_targets))
+            .add_systems(Update, search_for_targets.in_set(DefaultEnemyBehavior).after(select_targets))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Perception Components
+//==============================================================================================
+
+/// How an enemy senses the targets around it. Enemies without one know where everything is.
+/// Beacons are always known, they are what the enemies came for.
+#[derive(Component, Clone, Debug)]
+#[require(Awareness)]
+pub struct Perception {
+    pub sight_range : f32,
+    /// The full angle of the vision cone in radians.
+    pub field_of_view : f32,
+    /// Anything this close is noticed even when it is behind the enemy.
+    pub awareness_radius : f32,
+    /// Scales the radius of every noise the enemy could hear.
+    pub hearing : f32,
+    /// How many seconds a target is remembered after it was last seen or heard.
+    pub memory : f32,
+    pub eye_height : f32,
+}
+
+impl Perception {
+    pub fn new(sight_range : f32, field_of_view : f32, memory : f32) -> Self {
+        Perception {
+            sight_range,
+            field_of_view,
+            awareness_radius : 0.0,
+            hearing : 1.0,
+            memory,
+            eye_height : DEFAULT_EYE_HEIGHT,
+        }
+    }
+
+    pub fn with_awareness_radius(mut self, awareness_radius : f32) -> Self {
+        self.awareness_radius = awareness_radius;
+        self
+    }
+
+    pub fn with_hearing(mut self, hearing : f32) -> Self {
+        self.hearing = hearing;
+        self
+    }
+
+    pub fn with_eye_height(mut self, eye_height : f32) -> Self {
+        self.eye_height = eye_height;
+        self
+    }
+}
+
+/// A target an enemy has seen or heard.
+#[derive(Clone, Copy, Debug)]
+pub struct Sensed {
+    pub last_known_position : Vec3,
+    /// Whether the target was in sight the last time the enemy looked.
+    pub in_sight : bool,
+    /// Seconds since the target was last seen or heard.
+    pub age : f32,
+}
+
+/// Everything an enemy with [`Perception`] currently knows about.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Awareness(HashMap<Entity, Sensed>);
+
+impl Awareness {
+    pub fn get(&self, entity : Entity) -> Option<&Sensed> {
+        self.0.get(&entity)
+    }
+
+    pub fn knows(&self, entity : Entity) -> bool {
+        self.0.contains_key(&entity)
+    }
+
+    pub fn sees(&self, entity : Entity) -> bool {
+        self.get(entity).is_some_and(|sensed| sensed.in_sight)
+    }
+
+    pub fn last_known_position(&self, entity : Entity) -> Option<Vec3> {
+        self.get(entity).map(|sensed| sensed.last_known_position)
+    }
+
+    /// Tells this enemy what another one knows about a target.
+    pub fn share(&mut self, entity : Entity, sensed : Sensed) {
+        if self.sees(entity) { return }
+        self.0.insert(entity, Sensed { in_sight: false, ..sensed });
+    }
+
+    fn sense(&mut self, entity : Entity, position : Vec3, in_sight : bool) {
+        self.0.insert(entity, Sensed { last_known_position: position, in_sight, age: 0.0 });
+    }
+}
+
+/// What an enemy should do about a target it wants to attack. Targets in sight are attacked,
+/// targets that were only heard or have gone out of sight are searched for.
+pub fn pursue(target : Entity, awareness : Option<&Awareness>) -> EnemyBehavior {
+    let Some(awareness) = awareness else { return EnemyBehavior::AttackTarget };
+    if awareness.sees(target) { return EnemyBehavior::AttackTarget }
+    match awareness.last_known_position(target) {
+        Some(position) => EnemyBehavior::Search(position.xz()),
+        None => EnemyBehavior::Idle,
+    }
+}
+
+//==============================================================================================
+//        Noise
+//==============================================================================================
+
+/// Something loud happened. Enemies within the radius learn where it came from.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Noise {
+    pub position : Vec3,
+    pub radius : f32,
+    /// Who made the noise. Noises made by spells should name their caster.
+    pub source : Entity,
+}
+
+impl Noise {
+    pub fn new(position : Vec3, radius : f32, source : Entity) -> Self {
+        Noise { position, radius, source }
+    }
+}
+
+//==============================================================================================
+//        Perception Systems
+//==============================================================================================
+
+/// Checks every target that is not a landmark against the vision cone of the enemy, then casts
+/// a ray to it to make sure no obstacle is in the way.
+pub fn look_for_targets(
+    mut enemies : Query<(Entity, &GlobalTransform, &Perception, &mut Awareness, &Lod), (Without<Dying>, Without<Inactive>)>,
+    targets : Query<(Entity, &GlobalTransform, &Targetable, Option<&Health>), Without<Inactive>>,
+    spatial_query : SpatialQuery,
+    lod_frame : Res<LodFrame>,
+) {
+    let targets = targets.iter()
+        .filter(|(_, _, targetable, health)| !targetable.kind.is_landmark() && health.is_none_or(|health| health.current_health > 0.0))
+        .map(|(entity, transform, _, _)| (entity, transform.translation()))
+        .collect::<Vec<_>>();
+    let obstacle_filter = steering::obstacle_filter();
+
+    for (entity, transform, perception, mut awareness, lod) in enemies.iter_mut() {
+        if !lod_frame.should_update(entity, lod) { continue }
+        let eye = transform.translation() + Vec3::Y * perception.eye_height;
+        let forward = transform.forward().xz().normalize_or_zero();
+        // Targets that died, despawned or went inactive are no longer in the list, so everything
+        // starts out of sight and is only put back in sight if it is seen again.
+        for sensed in awareness.0.values_mut() {
+            sensed.in_sight = false;
+        }
+
+        for (target, target_position) in targets.iter() {
+            let offset = target_position.xz() - eye.xz();
+            let distance = offset.length();
+
+            let in_cone = distance <= perception.sight_range
+                && forward.angle_to(offset).abs() <= perception.field_of_view * 0.5;
+            if !(distance <= perception.awareness_radius || in_cone) { continue }
+
+            let line_of_sight = Dir3::new(*target_position - eye).ok()
+                .is_none_or(|direction| spatial_query.cast_ray(eye, direction, eye.distance(*target_position), true, &obstacle_filter).is_none());
+            if line_of_sight {
+                awareness.sense(*target, *target_position, true);
+            }
+        }
+    }
+}
+
+pub fn hear_noises(
+    mut noises : EventReader<Noise>,
+    mut enemies : Query<(&GlobalTransform, &Perception, &mut Awareness), (Without<Dying>, Without<Inactive>)>,
+    casters : Query<&Caster>,
+    targetables : Query<&GlobalTransform, With<Targetable>>,
+) {
+    for noise in noises.read() {
+        let source = casters.get(noise.source).map(|caster| caster.0).unwrap_or(noise.source);
+        let Ok(source_transform) = targetables.get(source) else { continue };
+        // A spell is heard where it lands, but it gives away where its caster is standing.
+        let source_position = source_transform.translation();
+
+        for (transform, perception, mut awareness) in enemies.iter_mut() {
+            if transform.translation().distance(noise.position) > noise.radius * perception.hearing { continue }
+            // Hearing something never takes it out of sight, it only moves where it is thought to be.
+            let in_sight = awareness.sees(source);
+            awareness.sense(source, source_position, in_sight);
+        }
+    }
+}
+
+pub fn forget_targets(
+    mut enemies : Query<(&Perception, &mut Awareness)>,
+    time : Res<Time>,
+) {
+    for (perception, mut awareness) in enemies.iter_mut() {
+        if awareness.0.is_empty() { continue }
+        awareness.0.retain(|_, sensed| {
+            if sensed.in_sight {
+                sensed.age = 0.0;
+            } else {
+                sensed.age += time.delta_secs();
+            }
+            sensed.age <= perception.memory
+        });
+    }
+}
+
+/// Walks to where the target was last seen or heard and looks around there, until the target
+/// turns up again or is forgotten about.
+pub fn search_for_targets(
+    mut enemies : Query<(Entity, &Transform, &mut SteeringAgent, &mut EnemyBehavior, &CurrentTarget, &Awareness, &Lod), Without<Staggered>>,
+    spatial_query : SpatialQuery,
+    lod_frame : Res<LodFrame>,
+    time : Res<Time>,
+) {
+    let obstacle_filter = steering::obstacle_filter();
+
+    for (entity, transform, mut agent, mut behavior, target, awareness, lod) in enemies.iter_mut() {
+        let EnemyBehavior::Search(search_position) = behavior.as_ref() else { continue };
+        if !lod_frame.should_update(entity, lod) { continue }
+
+        let Some(target) = target.entity() else {
+            *behavior = EnemyBehavior::Idle;
+            continue;
+        };
+        let next = pursue(target, Some(awareness));
+        if !matches!(next, EnemyBehavior::Search(_)) {
+            *behavior = next;
+            continue;
+        }
+
+        // A newer noise may have moved where the target is thought to be.
+        let EnemyBehavior::Search(last_known_position) = next else { continue };
+        if last_known_position != *search_position {
+            *behavior = EnemyBehavior::Search(last_known_position);
+        }
+
+        let position = transform.translation.xz();
+        if position.distance(last_known_position) > SEARCH_ARRIVE_DISTANCE {
+            let move_vector = steering::arrive(position, last_known_position, agent.max_speed, SEARCH_ARRIVE_DISTANCE);
+            let avoid_vector = steering::obstacle_avoidance(&spatial_query, transform.translation, move_vector, SEARCH_OBSTACLE_LOOK_AHEAD, &obstacle_filter);
+            agent.steer(move_vector + avoid_vector);
+            agent.face(move_vector);
+        } else {
+            // Nothing here, so turn on the spot in the hope of catching sight of it again.
+            let angle = time.elapsed_secs() * SEARCH_LOOK_AROUND_SPEED + entity.index() as f32;
+            agent.stop();
+            agent.face(Vec2::from_angle(angle));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::{assets::{EnemyAnimationGraphs, EnemyAssets}, enemy::{minion::{arm_minion, on_minion_scene_added, GroundWalker, Minion}, perception::Perception, threat::{TargetKind, TargetPriorities}, EnemyType, SpawnEnemy}, spells::damage::FrontShield, util::Health};
+
+const SHIELDBEARER_HEIGHT: f32 = 1.0;
+const SHIELDBEARER_RADIUS: f32 = 0.5;
+const SHIELDBEARER_HEALTH: f32 = 12.0;
+const SHIELDBEARER_SPEED: f32 = 2.0;
+/// The shield covers a bit more than the front half, it has to be hit from the side or behind.
+const SHIELDBEARER_SHIELD_ARC: f32 = 2.2;
+const SHIELDBEARER_SIGHT_RANGE: f32 = 8.0;
+const SHIELDBEARER_FIELD_OF_VIEW: f32 = 2.1;
+const SHIELDBEARER_AWARENESS_RADIUS: f32 = 3.0;
+const SHIELDBEARER_MEMORY: f32 = 5.0;
+const SHIELDBEARER_KNOCKBACK: f32 = 1.5;
+const SHIELDBEARER_STAGGER: f32 = 0.2;
+const SHIELDBEARER_HIT_FLASH: f32 = 0.12;
+const SHIELDBEARER_CORPSE_DURATION: f32 = 4.0;
+const SHIELDBEARER_PLAYER_PRIORITY: f32 = 3.0;
+const SHIELDBEARER_TURRET_PRIORITY: f32 = 2.0;
+const SHIELDBEARER_BEACON_PRIORITY: f32 = 1.0;
+const SHIELDBEARER_WALKER: GroundWalker = GroundWalker {
+    height : SHIELDBEARER_HEIGHT,
+    radius : SHIELDBEARER_RADIUS,
+    speed : SHIELDBEARER_SPEED,
+    knockback : SHIELDBEARER_KNOCKBACK,
+    stagger : SHIELDBEARER_STAGGER,
+    hit_flash : SHIELDBEARER_HIT_FLASH,
+    corpse_duration : SHIELDBEARER_CORPSE_DURATION,
+};
+
+//==============================================================================================
+//        Shieldbearer Plugin
+//==============================================================================================
+
+pub struct ShieldbearerPlugin;
+
+impl Plugin for ShieldbearerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ShieldbearerAssets>()
+            .add_observer(spawn_shieldbearer)
+        ;
+    }
+}
+
+//==============================================================================================
+//        Shieldbearer Component
+//==============================================================================================
+
+/// A slow, sturdy minion carrying a shield that blocks every spell coming at it from the front.
+/// It fights like any other [`Minion`], so it always turns to face what it is after.
+#[derive(Component)]
+pub struct Shieldbearer;
+
+#[derive(Resource)]
+pub struct ShieldbearerAssets {
+    shield_mesh : Handle<Mesh>,
+    shield_material : Handle<StandardMaterial>,
+}
+
+impl FromWorld for ShieldbearerAssets {
+    fn from_world(world: &mut World) -> Self {
+        let shield_mesh = world.resource_mut::<Assets<Mesh>>().add(Cuboid::new(1.0, 1.2, 0.1));
+        let shield_material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+            base_color : Color::srgb(0.45, 0.4, 0.35),
+            metallic : 0.8,
+            perceptual_roughness : 0.4,
+            ..default()
+        });
+        ShieldbearerAssets { shield_mesh, shield_material }
+    }
+}
+
+//==============================================================================================
+//        Spawn a Shieldbearer
+//==============================================================================================
+
+pub fn spawn_shieldbearer(
+    trigger : Trigger<SpawnEnemy>,
+    mut commands : Commands,
+    assets : Res<ShieldbearerAssets>,
+    enemy_assets : Res<EnemyAssets>,
+    enemy_animation_graphs : Res<EnemyAnimationGraphs>,
+) {
+    if trigger.enemy_type != EnemyType::Shieldbearer { return }
+    let position = trigger.position;
+
+    let mut shieldbearer = commands.spawn((
+        Name::new("Shieldbearer"),
+        SHIELDBEARER_WALKER.living(position, &enemy_animation_graphs),
+        SHIELDBEARER_WALKER.body(&enemy_assets, &enemy_animation_graphs),
+        (Shieldbearer, Minion::default(), EnemyType::Shieldbearer),
+        Health::new(SHIELDBEARER_HEALTH),
+        FrontShield { arc : SHIELDBEARER_SHIELD_ARC },
+        TargetPriorities::none()
+            .with_priority(TargetKind::Player, SHIELDBEARER_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Summon, SHIELDBEARER_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Turret, SHIELDBEARER_TURRET_PRIORITY, None)
+            .with_priority(TargetKind::Beacon, SHIELDBEARER_BEACON_PRIORITY, None),
+        Perception::new(SHIELDBEARER_SIGHT_RANGE, SHIELDBEARER_FIELD_OF_VIEW, SHIELDBEARER_MEMORY)
+            .with_awareness_radius(SHIELDBEARER_AWARENESS_RADIUS),
+        children![(
+            Mesh3d(assets.shield_mesh.clone()),
+            MeshMaterial3d(assets.shield_material.clone()),
+            Transform::from_xyz(0.0, 0.0, -(SHIELDBEARER_RADIUS + 0.1)),
+        )],
+    ));
+    shieldbearer.observe(on_minion_scene_added).observe(arm_minion);
+
+    trigger.insert_extras(&mut shieldbearer);
+}
@@ -0,0 +1,166 @@
+use std::{collections::VecDeque, f32::consts::TAU};
+
+use bevy::prelude::*;
+
+use crate::{arena::beacon::Beacon, enemy::{DefaultEnemyBehavior, Enemy, EnemyBehavior}};
+
+pub const SIEGE_SLOT_COUNT: usize = 8;
+pub const SIEGE_SLOT_RADIUS: f32 = 2.5;
+pub const SIEGE_QUEUE_RADIUS: f32 = 6.0;
+pub const SIEGE_SLOT_TOLERANCE: f32 = 0.75;
+
+//==============================================================================================
+//        Siege Plugin
+//==============================================================================================
+
+pub struct SiegePlugin;
+
+impl Plugin for SiegePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (release_siege_slots, queue_besiegers, assign_siege_slots).chain().in_set(DefaultEnemyBehavior))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Siege Components
+//==============================================================================================
+
+/// A ring of attack slots around a beacon. Only enemies that hold a slot are allowed to attack
+/// the beacon, everyone else waits in the queue until a slot frees up.
+#[derive(Component)]
+pub struct SiegeSlots {
+    pub radius : f32,
+    pub queue_radius : f32,
+    slots : Vec<Option<Entity>>,
+    queue : VecDeque<Entity>,
+}
+
+impl Default for SiegeSlots {
+    fn default() -> Self {
+        SiegeSlots::new(SIEGE_SLOT_COUNT, SIEGE_SLOT_RADIUS)
+    }
+}
+
+impl SiegeSlots {
+    pub fn new(count : usize, radius : f32) -> Self {
+        SiegeSlots {
+            radius,
+            queue_radius : SIEGE_QUEUE_RADIUS.max(radius),
+            slots : vec![None; count],
+            queue : VecDeque::new(),
+        }
+    }
+
+    /// The offset of a slot from the center of the beacon.
+    pub fn slot_offset(&self, slot : usize) -> Vec2 {
+        let angle = TAU * slot as f32 / self.slots.len().max(1) as f32;
+        Vec2::from_angle(angle) * self.radius
+    }
+
+    pub fn occupant(&self, slot : usize) -> Option<Entity> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    pub fn is_queued(&self, entity : Entity) -> bool {
+        self.queue.contains(&entity)
+    }
+
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Everyone holding a slot or waiting for one.
+    pub fn besieger_count(&self) -> usize {
+        self.slots.iter().flatten().count() + self.queue.len()
+    }
+
+    fn free_slot_closest_to(&self, offset : Vec2) -> Option<usize> {
+        self.slots.iter().enumerate()
+            .filter(|(_, occupant)| occupant.is_none())
+            .map(|(index, _)| index)
+            .min_by(|a, b| {
+                let a = self.slot_offset(*a).distance_squared(offset);
+                let b = self.slot_offset(*b).distance_squared(offset);
+                a.total_cmp(&b)
+            })
+    }
+}
+
+/// Added to an enemy that wants to attack a beacon. `slot` is `None` while the enemy is still
+/// waiting in the beacon's queue.
+#[derive(Component, Debug)]
+pub struct Besieging {
+    pub beacon : Entity,
+    pub slot : Option<usize>,
+}
+
+impl Besieging {
+    pub fn new(beacon : Entity) -> Self {
+        Besieging { beacon, slot: None }
+    }
+}
+
+//==============================================================================================
+//        Siege Systems
+//==============================================================================================
+
+/// Frees every slot and queue entry whose enemy is gone or no longer besieging this beacon.
+pub fn release_siege_slots(
+    mut beacons : Query<(Entity, &mut SiegeSlots), With<Beacon>>,
+    besiegers : Query<&Besieging, With<Enemy>>,
+) {
+    for (beacon, mut siege) in beacons.iter_mut() {
+        let still_besieging = |entity : Entity, slot : Option<usize>| {
+            besiegers.get(entity).map(|besieging| besieging.beacon == beacon && besieging.slot == slot).unwrap_or(false)
+        };
+
+        for index in 0..siege.slots.len() {
+            let Some(occupant) = siege.slots[index] else { continue };
+            if !still_besieging(occupant, Some(index)) {
+                siege.slots[index] = None;
+            }
+        }
+
+        siege.queue.retain(|entity| still_besieging(*entity, None));
+    }
+}
+
+/// Puts every enemy that started besieging a beacon at the back of that beacon's queue.
+pub fn queue_besiegers(
+    mut beacons : Query<&mut SiegeSlots, With<Beacon>>,
+    besiegers : Query<(Entity, &Besieging), (With<Enemy>, Added<Besieging>)>,
+) {
+    for (entity, besieging) in besiegers.iter() {
+        if besieging.slot.is_some() { continue; }
+        let Ok(mut siege) = beacons.get_mut(besieging.beacon) else { continue };
+        if !siege.is_queued(entity) {
+            siege.queue.push_back(entity);
+        }
+    }
+}
+
+/// Hands free slots to the front of the queue and sends those enemies to their slot.
+pub fn assign_siege_slots(
+    mut beacons : Query<(&Transform, &mut SiegeSlots), With<Beacon>>,
+    mut besiegers : Query<(&Transform, &mut Besieging, &mut EnemyBehavior), (With<Enemy>, Without<Beacon>)>,
+) {
+    for (beacon_transform, mut siege) in beacons.iter_mut() {
+        let beacon_position = beacon_transform.translation.xz();
+
+        while let Some(entity) = siege.queue.front().copied() {
+            let Ok((transform, mut besieging, mut behavior)) = besiegers.get_mut(entity) else {
+                siege.queue.pop_front();
+                continue;
+            };
+
+            let Some(slot) = siege.free_slot_closest_to(transform.translation.xz() - beacon_position) else { break };
+
+            siege.queue.pop_front();
+            siege.slots[slot] = Some(entity);
+            besieging.slot = Some(slot);
+            *behavior = EnemyBehavior::goto(beacon_position + siege.slot_offset(slot));
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::f32::consts::TAU;
+
+use avian3d::prelude::SpatialQuery;
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{arena::NavmeshQuery, enemy::{death::{Dying, EnemyDied}, hit_reaction::Staggered, perception::Awareness, threat::{select_targets, CurrentTarget}, DefaultEnemyBehavior, EnemyBehavior}, lod::{Lod, LodFrame}, steering::{self, SteeringAgent}};
+
+const SQUAD_SPACING: f32 = 1.5;
+const SQUAD_RING_RADIUS: f32 = 3.0;
+const FORMATION_SLOT_TOLERANCE: f32 = 0.5;
+const FORMATION_SLOWING_RADIUS: f32 = 1.5;
+const FORMATION_OBSTACLE_LOOK_AHEAD: f32 = 2.0;
+/// A member pathing to a slot it can't walk straight to only asks for a new path once the slot
+/// has moved this far from where it was headed.
+const FORMATION_REPATH_DISTANCE: f32 = 2.0;
+const ROUT_DURATION: f32 = 4.0;
+const ROUT_DISTANCE: f32 = 8.0;
+
+//==============================================================================================
+//        Squad Plugin
+//==============================================================================================
+
+pub struct SquadPlugin;
+
+impl Plugin for SquadPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_observer(scatter_on_leader_death)
+            .add_systems(Update, (
+                    (recover_from_rout, share_squad_targets).chain().after(select_targets),
+                    keep_formation,
+                )
+                .chain()
+                .in_set(DefaultEnemyBehavior)
+            )
+        ;
+    }
+}
+
+//==============================================================================================
+//        Formations
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Formation {
+    /// Side by side with the leader.
+    Line,
+    /// A V behind the leader.
+    Wedge,
+    /// Spread out around the squad's target, ready to surround it.
+    Ring,
+}
+
+impl Formation {
+    /// The offset of a slot from the leader as (right, back), or from the target for a ring.
+    pub fn offset(&self, slot : usize, slots : usize, spacing : f32) -> Vec2 {
+        let rank = (slot / 2 + 1) as f32;
+        let side = if slot.is_multiple_of(2) { 1.0 } else { -1.0 };
+        match self {
+            Formation::Line => Vec2::new(side * rank * spacing, 0.0),
+            Formation::Wedge => Vec2::new(side * rank * spacing, rank * spacing),
+            Formation::Ring => Vec2::from_angle(TAU * slot as f32 / slots.max(1) as f32) * SQUAD_RING_RADIUS.max(spacing),
+        }
+    }
+}
+
+//==============================================================================================
+//        Squad Components
+//==============================================================================================
+
+/// A group of enemies spawned together. They share one target and the members keep to their
+/// formation around the leader until the leader starts fighting.
+#[derive(Component, Debug)]
+pub struct Squad {
+    pub formation : Formation,
+    pub spacing : f32,
+    /// Picked from the members once the first one has spawned.
+    pub leader : Option<Entity>,
+}
+
+impl Squad {
+    pub fn new(formation : Formation) -> Self {
+        Squad { formation, spacing: SQUAD_SPACING, leader: None }
+    }
+}
+
+#[derive(Component)]
+#[relationship(relationship_target = SquadMembers)]
+pub struct SquadMember(pub Entity);
+
+#[derive(Component, Deref)]
+#[relationship_target(relationship = SquadMember)]
+pub struct SquadMembers(Vec<Entity>);
+
+/// A squad member whose leader died. It runs off and ignores every target until its nerve returns.
+#[derive(Component)]
+pub struct Routed(pub Timer);
+
+//==============================================================================================
+//        Squad Systems
+//==============================================================================================
+
+/// Everyone in the squad goes after whatever the leader is after. A leader without a target
+/// takes the one the first of its members has found.
+pub fn share_squad_targets(
+    mut commands : Commands,
+    mut squads : Query<(Entity, &mut Squad, Option<&SquadMembers>)>,
+    mut members : Query<(&mut CurrentTarget, Option<&mut Awareness>), (Without<Dying>, Without<Routed>)>,
+) {
+    for (squad_entity, mut squad, squad_members) in squads.iter_mut() {
+        let Some(squad_members) = squad_members.filter(|squad_members| !squad_members.is_empty()) else {
+            // Everyone is dead or scattered, the squad is over.
+            commands.entity(squad_entity).despawn();
+            continue;
+        };
+
+        if squad.leader.is_none_or(|leader| !squad_members.contains(&leader)) {
+            squad.leader = squad_members.first().copied();
+        }
+        let Some(leader) = squad.leader else { continue };
+
+        let target = std::iter::once(leader).chain(squad_members.iter())
+            .filter_map(|member| members.get(member).ok())
+            .find_map(|(target, awareness)| {
+                let (entity, kind) = target.0?;
+                Some((entity, kind, awareness.and_then(|awareness| awareness.get(entity).copied())))
+            });
+        let Some((target, kind, sensed)) = target else { continue };
+
+        for member in squad_members.iter() {
+            let Ok((mut current, awareness)) = members.get_mut(member) else { continue };
+            current.0 = Some((target, kind));
+            if let (Some(mut awareness), Some(sensed)) = (awareness, sensed) {
+                awareness.share(target, sensed);
+            }
+        }
+    }
+}
+
+/// Members follow the leader in formation until the leader starts fighting, then they are let
+/// loose to fight on their own. In a ring they first spread out around the target.
+pub fn keep_formation(
+    squads : Query<(&Squad, &SquadMembers)>,
+    mut members : Query<(&Transform, &mut EnemyBehavior, &mut SteeringAgent, &CurrentTarget, &Lod, Has<Staggered>), (Without<Routed>, Without<Dying>)>,
+    targets : Query<&GlobalTransform>,
+    navmesh : NavmeshQuery,
+    spatial_query : SpatialQuery,
+    lod_frame : Res<LodFrame>,
+) {
+    let obstacle_filter = steering::obstacle_filter();
+
+    for (squad, squad_members) in squads.iter() {
+        let Some(leader) = squad.leader else { continue };
+        let Ok((leader_transform, leader_behavior, _, leader_target, _, _)) = members.get(leader) else { continue };
+
+        let leader_position = leader_transform.translation.xz();
+        let forward = leader_transform.forward().xz().normalize_or(Vec2::NEG_Y);
+        let right = Vec2::new(-forward.y, forward.x);
+        let engaged = matches!(leader_behavior, EnemyBehavior::AttackTarget | EnemyBehavior::AttackBeacon | EnemyBehavior::Search(_));
+        let ring_center = leader_target.entity()
+            .and_then(|target| targets.get(target).ok())
+            .map(|target| target.translation().xz())
+            .filter(|_| squad.formation == Formation::Ring);
+
+        let slots = squad_members.len().saturating_sub(1);
+        for (slot, member) in squad_members.iter().filter(|member| *member != leader).enumerate() {
+            let Ok((transform, mut behavior, mut agent, _, lod, staggered)) = members.get_mut(member) else { continue };
+            if staggered || !lod_frame.should_update(member, lod) { continue }
+
+            let offset = squad.formation.offset(slot, slots, squad.spacing);
+            let slot_position = match ring_center {
+                Some(center) => center + offset,
+                None => leader_position + right * offset.x - forward * offset.y,
+            };
+            // Slots that end up off the navmesh just bunch up behind the leader.
+            let slot_position = if navmesh.is_walkable(slot_position) { slot_position } else { leader_position };
+            let position = transform.translation.xz();
+            let at_slot = position.distance(slot_position) <= FORMATION_SLOT_TOLERANCE;
+
+            let in_formation = matches!(behavior.as_ref(), EnemyBehavior::InFormation);
+            if engaged {
+                if !in_formation { continue }
+                if ring_center.is_none() || at_slot {
+                    *behavior = EnemyBehavior::Idle;
+                    continue;
+                }
+            } else if !(in_formation || matches!(behavior.as_ref(), EnemyBehavior::Idle | EnemyBehavior::Goto(..) | EnemyBehavior::Approach(..))) {
+                continue;
+            }
+
+            // A slot behind a rock or a wall is reached the long way round, over the navmesh like the leader.
+            if !at_slot && navmesh.is_built() && !navmesh.is_path_clear(&[position, slot_position]) {
+                let heading_there = matches!(behavior.as_ref(), EnemyBehavior::Goto(destination, ..) if destination.distance(slot_position) <= FORMATION_REPATH_DISTANCE);
+                if !heading_there {
+                    *behavior = EnemyBehavior::goto(slot_position);
+                }
+                continue;
+            }
+
+            *behavior = EnemyBehavior::InFormation;
+            if at_slot {
+                agent.stop();
+                agent.face(forward);
+            } else {
+                let move_vector = steering::arrive(position, slot_position, agent.max_speed, FORMATION_SLOWING_RADIUS);
+                let avoid_vector = steering::obstacle_avoidance(&spatial_query, transform.translation, move_vector, FORMATION_OBSTACLE_LOOK_AHEAD, &obstacle_filter);
+                agent.steer(move_vector + avoid_vector);
+            }
+        }
+    }
+}
+
+/// Losing the leader breaks the squad. Every member drops its target and runs away from the body.
+pub fn scatter_on_leader_death(
+    trigger : Trigger<EnemyDied>,
+    mut commands : Commands,
+    squads : Query<(Entity, &Squad, Option<&SquadMembers>)>,
+    mut members : Query<(&Transform, &mut EnemyBehavior, &mut CurrentTarget)>,
+    navmesh : NavmeshQuery,
+) {
+    let Some((squad_entity, _, squad_members)) = squads.iter().find(|(_, squad, _)| squad.leader == Some(trigger.target())) else { return };
+
+    let mut rng = rand::rng();
+    for member in squad_members.into_iter().flat_map(|squad_members| squad_members.iter()) {
+        let Ok((transform, mut behavior, mut target)) = members.get_mut(member) else { continue };
+        let position = transform.translation.xz();
+        let away = (position - trigger.position.xz()).normalize_or(Vec2::from_angle(rng.random_range(0.0..TAU)));
+        let flee_to = position + away * ROUT_DISTANCE;
+
+        target.0 = None;
+        if navmesh.is_walkable(flee_to) {
+            *behavior = EnemyBehavior::goto(flee_to);
+        }
+        commands.entity(member)
+            .remove::<SquadMember>()
+            .insert(Routed(Timer::from_seconds(ROUT_DURATION, TimerMode::Once)));
+    }
+    commands.entity(squad_entity).despawn();
+}
+
+pub fn recover_from_rout(
+    mut commands : Commands,
+    mut routed : Query<(Entity, &mut Routed, &mut CurrentTarget)>,
+    time : Res<Time>,
+) {
+    for (entity, mut routed, mut target) in routed.iter_mut() {
+        target.0 = None;
+        routed.0.tick(time.delta());
+        if routed.0.finished() {
+            commands.entity(entity).remove::<Routed>();
+        }
+    }
+}
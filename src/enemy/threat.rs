@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use strum::EnumCount;
+
+use crate::{arena::beacon::BeaconQuery, enemy::{death::Dying, orders::{Returning, StandingOrders}, perception::Awareness, DefaultEnemyBehavior}, lod::{Lod, LodFrame}, pool::Inactive, spells::{damage::Damage, Caster}, util::Health};
+
+const THREAT_HALF_LIFE: f32 = 3.0;
+/// Threat below this is forgotten about entirely.
+const THREAT_FORGET: f32 = 0.25;
+const DEFAULT_DISTANCE_FALLOFF: f32 = 0.1;
+const DEFAULT_THREAT_WEIGHT: f32 = 0.5;
+const DEFAULT_SWITCH_MARGIN: f32 = 0.25;
+/// How much more the most threatened beacon is worth, so enemies pile onto a beacon that is already falling.
+const MOST_THREATENED_BEACON_BONUS: f32 = 0.5;
+
+//==============================================================================================
+//        Threat Plugin
+//==============================================================================================
+
+pub struct ThreatPlugin;
+
+impl Plugin for ThreatPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_observer(record_threat)
+            .add_systems(Update, (decay_threat, select_targets).chain().in_set(DefaultEnemyBehavior))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Targets
+//==============================================================================================
+
+#[repr(usize)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, EnumCount)]
+pub enum TargetKind {
+    Player,
+    Beacon,
+    Summon,
+    Turret,
+}
+
+impl TargetKind {
+    /// Landmarks are known to every enemy from the start, anything else has to be perceived first.
+    pub fn is_landmark(&self) -> bool {
+        matches!(self, TargetKind::Beacon)
+    }
+}
+
+/// Something enemies are allowed to go after.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Targetable {
+    pub kind : TargetKind,
+}
+
+impl Targetable {
+    pub fn new(kind : TargetKind) -> Self {
+        Targetable { kind }
+    }
+}
+
+//==============================================================================================
+//        Threat Components
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug)]
+pub struct TargetPriority {
+    pub weight : f32,
+    /// Targets further away than this are ignored unless they have threat on the enemy.
+    pub range : Option<f32>,
+}
+
+/// How much an enemy type cares about each kind of target. Kinds without a priority are never picked.
+#[derive(Component, Clone, Debug)]
+#[require(ThreatTable, CurrentTarget)]
+pub struct TargetPriorities {
+    priorities : [Option<TargetPriority>; TargetKind::COUNT],
+    /// How fast the weight of a target drops off with distance.
+    pub distance_falloff : f32,
+    /// How much score each point of recent damage is worth.
+    pub threat_weight : f32,
+    /// How much better, as a fraction, another target has to score before the current one is dropped.
+    pub switch_margin : f32,
+}
+
+impl Default for TargetPriorities {
+    fn default() -> Self {
+        TargetPriorities {
+            priorities : [Some(TargetPriority { weight: 1.0, range: None }); TargetKind::COUNT],
+            distance_falloff : DEFAULT_DISTANCE_FALLOFF,
+            threat_weight : DEFAULT_THREAT_WEIGHT,
+            switch_margin : DEFAULT_SWITCH_MARGIN,
+        }
+    }
+}
+
+impl TargetPriorities {
+    /// Starts out ignoring every kind of target.
+    pub fn none() -> Self {
+        TargetPriorities { priorities: [None; TargetKind::COUNT], ..default() }
+    }
+
+    pub fn with_priority(mut self, kind : TargetKind, weight : f32, range : Option<f32>) -> Self {
+        self.priorities[kind as usize] = Some(TargetPriority { weight, range });
+        self
+    }
+
+    pub fn without(mut self, kind : TargetKind) -> Self {
+        self.priorities[kind as usize] = None;
+        self
+    }
+
+    pub fn priority(&self, kind : TargetKind) -> Option<TargetPriority> {
+        self.priorities[kind as usize]
+    }
+
+    /// The score of a target, `None` when the enemy would not go after it at all.
+    pub fn score(&self, kind : TargetKind, distance : f32, threat : f32) -> Option<f32> {
+        let priority = self.priority(kind)?;
+        let in_range = priority.range.is_none_or(|range| distance <= range);
+        if !in_range && threat <= 0.0 { return None }
+        Some(priority.weight / (1.0 + distance * self.distance_falloff) + threat * self.threat_weight)
+    }
+}
+
+/// The damage an enemy took recently, by whoever dealt it. Decays over time.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ThreatTable(HashMap<Entity, f32>);
+
+impl ThreatTable {
+    pub fn threat(&self, entity : Entity) -> f32 {
+        self.0.get(&entity).copied().unwrap_or(0.0)
+    }
+
+    pub fn add(&mut self, entity : Entity, amount : f32) {
+        *self.0.entry(entity).or_default() += amount;
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// The target an enemy has committed to.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct CurrentTarget(pub Option<(Entity, TargetKind)>);
+
+impl CurrentTarget {
+    pub fn entity(&self) -> Option<Entity> {
+        self.0.map(|(entity, _)| entity)
+    }
+
+    pub fn kind(&self) -> Option<TargetKind> {
+        self.0.map(|(_, kind)| kind)
+    }
+
+    pub fn is(&self, kind : TargetKind) -> bool {
+        self.kind() == Some(kind)
+    }
+}
+
+//==============================================================================================
+//        Threat Systems
+//==============================================================================================
+
+/// Damage from a spell is blamed on whoever cast it, only damage from something targetable counts.
+pub fn record_threat(
+    trigger : Trigger<Damage>,
+    mut enemies : Query<&mut ThreatTable, Without<Dying>>,
+    casters : Query<&Caster>,
+    targetables : Query<(), With<Targetable>>,
+) {
+    let Some(source) = trigger.source else { return };
+    let Ok(mut threat) = enemies.get_mut(trigger.target()) else { return };
+    let source = casters.get(source).map(|caster| caster.0).unwrap_or(source);
+    if !targetables.contains(source) { return }
+    threat.add(source, trigger.amount);
+}
+
+pub fn decay_threat(
+    mut tables : Query<&mut ThreatTable>,
+    time : Res<Time>,
+) {
+    let decay = 0.5_f32.powf(time.delta_secs() / THREAT_HALF_LIFE);
+    for mut table in tables.iter_mut() {
+        if table.0.is_empty() { continue; }
+        table.0.retain(|_, threat| {
+            *threat *= decay;
+            *threat >= THREAT_FORGET
+        });
+    }
+}
+
+/// Scores every target for every enemy and switches targets only once another one clearly beats
+/// the current one. Enemies with an [`Awareness`] only score what they know about, from where
+/// they last saw or heard it, and enemies with [`StandingOrders`] ignore landmarks. Of the beacons,
+/// only the nearest one and the most threatened one are considered.
+pub fn select_targets(
+    mut enemies : Query<(Entity, &Transform, &TargetPriorities, &ThreatTable, &mut CurrentTarget, Option<&Awareness>, Has<StandingOrders>, &Lod), (Without<Dying>, Without<Inactive>, Without<Returning>)>,
+    targets : Query<(Entity, &GlobalTransform, &Targetable, Option<&Health>), Without<Inactive>>,
+    beacons : BeaconQuery,
+    lod_frame : Res<LodFrame>,
+) {
+    let most_threatened = beacons.most_threatened();
+    let targets = targets.iter()
+        .filter(|(_, _, _, health)| health.is_none_or(|health| health.current_health > 0.0))
+        .map(|(entity, transform, targetable, _)| (entity, transform.translation().xz(), targetable.kind))
+        .collect::<Vec<_>>();
+
+    enemies.par_iter_mut().for_each(|(entity, transform, priorities, threat, mut current, awareness, has_orders, lod)| {
+        if !lod_frame.should_update(entity, lod) { return };
+        let position = transform.translation.xz();
+        let nearest_beacon = beacons.nearest(position);
+
+        let scores = targets.iter().filter_map(|(target, target_position, kind)| {
+            if has_orders && kind.is_landmark() { return None }
+            let is_most_threatened = most_threatened == Some(*target);
+            if *kind == TargetKind::Beacon && !is_most_threatened && nearest_beacon != Some(*target) { return None }
+            let target_position = match awareness {
+                Some(awareness) if !kind.is_landmark() => awareness.last_known_position(*target)?.xz(),
+                _ => *target_position,
+            };
+            let score = priorities.score(*kind, position.distance(target_position), threat.threat(*target))?;
+            let bonus = if *kind == TargetKind::Beacon && is_most_threatened { 1.0 + MOST_THREATENED_BEACON_BONUS } else { 1.0 };
+            Some((*target, *kind, score * bonus))
+        });
+
+        let mut best : Option<(Entity, TargetKind, f32)> = None;
+        let mut current_score = None;
+        for (target, kind, score) in scores {
+            if current.entity() == Some(target) { current_score = Some(score) }
+            if best.is_none_or(|(_, _, best)| score > best) { best = Some((target, kind, score)) }
+        }
+
+        let keep_current = match (current_score, best) {
+            (Some(current_score), Some((_, _, best))) => current_score * (1.0 + priorities.switch_margin) >= best,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if keep_current { return }
+
+        let next = best.map(|(target, kind, _)| (target, kind));
+        if current.0 != next { current.0 = next }
+    });
+}
@@ -0,0 +1,119 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{enemy::{death::DeathSequence, flying::{flying_body, Flyer}, hit_reaction::HitReaction, perception::Perception, threat::{TargetKind, TargetPriorities}, Enemy, EnemyBehavior, EnemyType, SpawnEnemy}, steering::SteeringAgent, util::{GameCollisionLayer, Health}};
+
+const WISP_ALTITUDE: f32 = 5.0;
+const WISP_RADIUS: f32 = 0.3;
+const WISP_HEALTH: f32 = 3.0;
+const WISP_SPEED: f32 = 4.5;
+const WISP_SWOOP_DAMAGE: f32 = 4.0;
+const WISP_SWOOP_COOLDOWN: f32 = 3.0;
+const WISP_SIGHT_RANGE: f32 = 12.0;
+const WISP_FIELD_OF_VIEW: f32 = 3.0;
+const WISP_MEMORY: f32 = 3.0;
+const WISP_KNOCKBACK: f32 = 6.0;
+const WISP_HIT_FLASH: f32 = 0.1;
+const WISP_CORPSE_DURATION: f32 = 2.0;
+const WISP_PLAYER_PRIORITY: f32 = 3.0;
+const WISP_BEACON_PRIORITY: f32 = 1.0;
+
+//==============================================================================================
+//        Wisp Plugin
+//==============================================================================================
+
+pub struct WispPlugin;
+
+impl Plugin for WispPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<WispAssets>()
+            .add_observer(spawn_wisp)
+        ;
+    }
+}
+
+//==============================================================================================
+//        Wisp Component
+//==============================================================================================
+
+/// A small glowing flyer that circles above the fight and swoops down at whatever it is after.
+#[derive(Component)]
+pub struct Wisp;
+
+#[derive(Resource)]
+pub struct WispAssets {
+    mesh : Handle<Mesh>,
+    material : Handle<StandardMaterial>,
+}
+
+impl FromWorld for WispAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(WISP_RADIUS));
+        let color = Color::srgb(0.5, 0.9, 1.0);
+        let material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+            base_color : color,
+            emissive : color.to_linear() * 4.0,
+            ..default()
+        });
+        WispAssets { mesh, material }
+    }
+}
+
+//==============================================================================================
+//        Spawn a Wisp
+//==============================================================================================
+
+pub fn spawn_wisp(
+    trigger : Trigger<SpawnEnemy>,
+    mut commands : Commands,
+    assets : Res<WispAssets>,
+) {
+    if trigger.enemy_type != EnemyType::Wisp { return }
+    let position = trigger.position;
+
+    let mut wisp = commands.spawn((
+        Name::new("Wisp"),
+        Transform::from_translation(Vec3::new(position.x, WISP_ALTITUDE, position.z)),
+        (Wisp, EnemyType::Wisp),
+        Enemy {
+            height_from_ground : WISP_ALTITUDE,
+            speed : WISP_SPEED,
+        },
+        Health::new(WISP_HEALTH),
+        EnemyBehavior::Idle,
+        CollisionLayers::new(GameCollisionLayer::Enemy, [
+            GameCollisionLayer::Player,
+            GameCollisionLayer::Default,
+            GameCollisionLayer::Spell,
+            GameCollisionLayer::Obstacle,
+        ]),
+        flying_body(),
+        Collider::sphere(WISP_RADIUS),
+        Flyer::new(WISP_ALTITUDE, WISP_SWOOP_DAMAGE, WISP_SWOOP_COOLDOWN),
+        SteeringAgent::new(WISP_RADIUS, WISP_SPEED),
+        TargetPriorities::none()
+            .with_priority(TargetKind::Player, WISP_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Summon, WISP_PLAYER_PRIORITY, None)
+            .with_priority(TargetKind::Beacon, WISP_BEACON_PRIORITY, None),
+        Perception::new(WISP_SIGHT_RANGE, WISP_FIELD_OF_VIEW, WISP_MEMORY).with_eye_height(0.0),
+        (
+            HitReaction {
+                knockback : WISP_KNOCKBACK,
+                flash_color : Color::WHITE,
+                flash_duration : WISP_HIT_FLASH,
+                ..default()
+            },
+            DeathSequence {
+                animation : None,
+                collapse : true,
+                despawn_delay : WISP_CORPSE_DURATION,
+            },
+        ),
+        children![(
+            Mesh3d(assets.mesh.clone()),
+            MeshMaterial3d(assets.material.clone()),
+        )],
+    ));
+    trigger.insert_extras(&mut wisp);
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+
+use crate::{camera::MainCamera, character::PlayerCharacter, enemy::EnemyBehavior, pool::Inactive, util::AnimationControlerFor};
+
+const LOD_NEAR_DISTANCE: f32 = 15.0;
+const LOD_FAR_DISTANCE: f32 = 30.0;
+const LOD_HYSTERESIS: f32 = 2.0;
+/// How far past the edge of the screen something has to move, in NDC, before it counts as on or off screen.
+const LOD_SCREEN_MARGIN: f32 = 0.1;
+
+//==============================================================================================
+//        Lod Plugin
+//==============================================================================================
+
+pub struct LodPlugin;
+
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LodFrame>()
+            .init_resource::<LodSettings>()
+
+            .add_systems(First, advance_lod_frame)
+            .add_systems(Update, update_lod_tiers.in_set(LodSet))
+        ;
+    }
+}
+
+/// Puts everything with a [`Lod`] into its tier. Runs before any of the AI does.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LodSet;
+
+//==============================================================================================
+//        Lod Components
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LodTier {
+    #[default]
+    Near,
+    Mid,
+    Far,
+}
+
+impl LodTier {
+    /// Every how many frames the AI of this tier is updated.
+    pub fn update_interval(&self) -> u32 {
+        match self {
+            LodTier::Near => 1,
+            LodTier::Mid => 2,
+            LodTier::Far => 4,
+        }
+    }
+
+    pub fn freezes_animation(&self) -> bool {
+        matches!(self, LodTier::Far)
+    }
+
+    fn farther(self) -> Self {
+        match self {
+            LodTier::Near => LodTier::Mid,
+            _ => LodTier::Far,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lod {
+    pub tier : LodTier,
+    on_screen : bool,
+    /// Whether the animations of the entity are paused.
+    frozen : bool,
+}
+
+impl Default for Lod {
+    fn default() -> Self {
+        Lod { tier: LodTier::Near, on_screen: true, frozen: false }
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct LodSettings {
+    pub near_distance : f32,
+    pub far_distance : f32,
+    /// How far past a boundary something has to move before it changes tier.
+    pub hysteresis : f32,
+    /// The same as `hysteresis`, for the edge of the screen in NDC.
+    pub screen_margin : f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        LodSettings {
+            near_distance : LOD_NEAR_DISTANCE,
+            far_distance : LOD_FAR_DISTANCE,
+            hysteresis : LOD_HYSTERESIS,
+            screen_margin : LOD_SCREEN_MARGIN,
+        }
+    }
+}
+
+impl LodSettings {
+    fn tier_for(&self, distance : f32, current : LodTier) -> LodTier {
+        let near = self.near_distance + if current == LodTier::Near { self.hysteresis } else { -self.hysteresis };
+        let far = self.far_distance + if current == LodTier::Far { -self.hysteresis } else { self.hysteresis };
+        if distance < near {
+            LodTier::Near
+        } else if distance < far {
+            LodTier::Mid
+        } else {
+            LodTier::Far
+        }
+    }
+
+    fn is_on_screen(&self, ndc : Vec3, was_on_screen : bool) -> bool {
+        let edge = 1.0 + if was_on_screen { self.screen_margin } else { -self.screen_margin };
+        ndc.x.abs() <= edge && ndc.y.abs() <= edge && (0.0..=1.0).contains(&ndc.z)
+    }
+}
+
+/// Counts frames so that the updates of a tier can be spread out over several frames.
+#[derive(Resource, Default)]
+pub struct LodFrame(u32);
+
+impl LodFrame {
+    /// Whether the entity gets its update this frame. Entities are staggered by their index, so a
+    /// tier that updates every fourth frame still has a quarter of its entities updating each frame.
+    pub fn should_update(&self, entity : Entity, lod : &Lod) -> bool {
+        self.0.wrapping_add(entity.index()).is_multiple_of(lod.tier.update_interval())
+    }
+}
+
+//==============================================================================================
+//        Lod Systems
+//==============================================================================================
+
+pub fn advance_lod_frame(mut frame : ResMut<LodFrame>) {
+    frame.0 = frame.0.wrapping_add(1);
+}
+
+/// Tiers by distance to the player. Anything off screen is pushed out by one tier. Enemies that
+/// are still spawning keep animating, their spawn animation is what lets them go.
+pub fn update_lod_tiers(
+    mut lods : Query<(&GlobalTransform, &mut Lod, Option<&AnimationControlerFor>, Option<&EnemyBehavior>), Without<Inactive>>,
+    player : Single<&Transform, With<PlayerCharacter>>,
+    camera : Option<Single<(&Camera, &GlobalTransform), With<MainCamera>>>,
+    mut animation_players : Query<&mut AnimationPlayer>,
+    settings : Res<LodSettings>,
+) {
+    let camera = camera.as_deref();
+
+    for (transform, mut lod, animated_models, behavior) in lods.iter_mut() {
+        let position = transform.translation();
+        let mut tier = settings.tier_for(position.distance(player.translation), lod.tier);
+
+        let on_screen = camera
+            .and_then(|(camera, camera_transform)| camera.world_to_ndc(camera_transform, position))
+            .map(|ndc| settings.is_on_screen(ndc, lod.on_screen))
+            .unwrap_or(true);
+        if !on_screen {
+            tier = tier.farther();
+        }
+
+        let spawning = matches!(behavior, Some(EnemyBehavior::Spawning));
+        let frozen = tier.freezes_animation() && !spawning;
+        let next = Lod { tier, on_screen, frozen };
+        if *lod == next { continue; }
+        let was_frozen = lod.frozen;
+        *lod = next;
+
+        if was_frozen == frozen { continue; }
+        for model in animated_models.into_iter().flat_map(|models| models.iter()) {
+            let Ok(mut player) = animation_players.get_mut(model) else { continue };
+            if frozen {
+                player.pause_all();
+            } else {
+                player.resume_all();
+            }
+        }
+    }
+}
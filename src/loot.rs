@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use rand::Rng;
+use weighted_rand::{builder::{NewBuilder, WalkerTableBuilder}, table::WalkerTable};
+
+use crate::{character::{Experience, Mana, PlayerCharacter, SpellScrolls}, enemy::{death::EnemyDied, elite::Elite, EnemyType}, util::{GameCollisionLayer, Health}, GameState};
+
+const PICKUP_RADIUS: f32 = 4.0;
+const PICKUP_ACCELERATION: f32 = 30.0;
+const PICKUP_MAX_SPEED: f32 = 15.0;
+const PICKUP_HEIGHT: f32 = 0.5;
+const PICKUP_SCATTER: f32 = 0.75;
+const PICKUP_SIZE: f32 = 0.15;
+const ELITE_BONUS_ROLLS_PER_AFFIX: u32 = 2;
+
+//==============================================================================================
+//        Loot Plugin
+//==============================================================================================
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LootTables>()
+            .init_resource::<PickupAssets>()
+
+            .add_observer(drop_loot)
+            .add_observer(apply_pickup)
+
+            .add_systems(Update, magnetize_pickups.run_if(in_state(GameState::InGame)))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Loot
+//==============================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Loot {
+    Experience(u32),
+    Health(f32),
+    Mana(f32),
+    SpellScroll,
+}
+
+impl Loot {
+    fn color(&self) -> Color {
+        match self {
+            Loot::Experience(_) => Color::srgb(0.3, 1.0, 0.4),
+            Loot::Health(_) => Color::srgb(1.0, 0.2, 0.2),
+            Loot::Mana(_) => Color::srgb(0.2, 0.4, 1.0),
+            Loot::SpellScroll => Color::srgb(1.0, 0.8, 0.2),
+        }
+    }
+}
+
+//==============================================================================================
+//        Loot Tables
+//==============================================================================================
+
+/// What an enemy type can drop. Every roll picks one entry by weight, `None` entries drop nothing.
+pub struct LootTable {
+    rolls : u32,
+    entries : Vec<Option<Loot>>,
+    table : WalkerTable,
+}
+
+impl LootTable {
+    pub fn new(rolls : u32, entries : &[(Option<Loot>, u32)]) -> Self {
+        let weights = entries.iter().map(|(_, weight)| *weight).collect::<Vec<_>>();
+        LootTable {
+            rolls,
+            entries : entries.iter().map(|(loot, _)| *loot).collect(),
+            table : WalkerTableBuilder::new(&weights).build(),
+        }
+    }
+
+    /// Rolls the table, `bonus_rolls` on top of the table's own rolls.
+    pub fn roll(&self, bonus_rolls : u32) -> Vec<Loot> {
+        (0..self.rolls + bonus_rolls).filter_map(|_| self.entries.get(self.table.next()).copied().flatten()).collect()
+    }
+}
+
+#[derive(Resource)]
+pub struct LootTables(HashMap<EnemyType, LootTable>);
+
+impl LootTables {
+    pub fn get(&self, enemy_type : &EnemyType) -> Option<&LootTable> {
+        self.0.get(enemy_type)
+    }
+
+    pub fn insert(&mut self, enemy_type : EnemyType, table : LootTable) {
+        self.0.insert(enemy_type, table);
+    }
+}
+
+impl Default for LootTables {
+    fn default() -> Self {
+        let mut tables = HashMap::new();
+        tables.insert(EnemyType::Minion, LootTable::new(1, &[
+            (None, 40),
+            (Some(Loot::Experience(1)), 45),
+            (Some(Loot::Health(10.0)), 8),
+            (Some(Loot::Mana(10.0)), 6),
+            (Some(Loot::SpellScroll), 1),
+        ]));
+        tables.insert(EnemyType::Mage, LootTable::new(2, &[
+            (None, 30),
+            (Some(Loot::Experience(3)), 45),
+            (Some(Loot::Health(10.0)), 8),
+            (Some(Loot::Mana(20.0)), 14),
+            (Some(Loot::SpellScroll), 3),
+        ]));
+        tables.insert(EnemyType::Wisp, LootTable::new(1, &[
+            (None, 45),
+            (Some(Loot::Experience(2)), 40),
+            (Some(Loot::Mana(15.0)), 15),
+        ]));
+        tables.insert(EnemyType::Bomber, LootTable::new(1, &[
+            (None, 45),
+            (Some(Loot::Experience(2)), 45),
+            (Some(Loot::Health(10.0)), 10),
+        ]));
+        tables.insert(EnemyType::Shieldbearer, LootTable::new(2, &[
+            (None, 30),
+            (Some(Loot::Experience(3)), 45),
+            (Some(Loot::Health(15.0)), 15),
+            (Some(Loot::SpellScroll), 10),
+        ]));
+        tables.insert(EnemyType::SkeletonMageBoss, LootTable::new(8, &[
+            (Some(Loot::Experience(10)), 50),
+            (Some(Loot::Health(25.0)), 20),
+            (Some(Loot::Mana(25.0)), 20),
+            (Some(Loot::SpellScroll), 10),
+        ]));
+        LootTables(tables)
+    }
+}
+
+//==============================================================================================
+//        Pickups
+//==============================================================================================
+
+/// Loot lying in the arena. It gets pulled towards the player once they are in range.
+#[derive(Component, Debug)]
+pub struct Pickup {
+    pub loot : Loot,
+    speed : f32,
+}
+
+/// Triggered on the player when they walk into a pickup.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PickupCollected(pub Loot);
+
+#[derive(Resource)]
+pub struct PickupAssets {
+    mesh : Handle<Mesh>,
+    materials : HashMap<[u8; 4], Handle<StandardMaterial>>,
+}
+
+impl FromWorld for PickupAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(PICKUP_SIZE));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = [Loot::Experience(0), Loot::Health(0.0), Loot::Mana(0.0), Loot::SpellScroll].into_iter()
+            .map(|loot| {
+                let color = loot.color();
+                (color.to_srgba().to_u8_array(), materials.add(StandardMaterial {
+                    base_color : color,
+                    emissive : color.to_linear() * 2.0,
+                    ..default()
+                }))
+            })
+            .collect();
+        PickupAssets { mesh, materials }
+    }
+}
+
+impl PickupAssets {
+    fn material(&self, loot : &Loot) -> Handle<StandardMaterial> {
+        self.materials[&loot.color().to_srgba().to_u8_array()].clone()
+    }
+}
+
+pub fn spawn_pickup(commands : &mut Commands, assets : &PickupAssets, loot : Loot, position : Vec3) {
+    commands.spawn((
+        Name::new("Pickup"),
+        Pickup { loot, speed: 0.0 },
+        Transform::from_translation(position),
+        Mesh3d(assets.mesh.clone()),
+        MeshMaterial3d(assets.material(&loot)),
+        RigidBody::Kinematic,
+        Collider::sphere(PICKUP_SIZE * 2.0),
+        Sensor,
+        CollisionEventsEnabled,
+        CollisionLayers::new(GameCollisionLayer::Pickup, GameCollisionLayer::Player),
+    )).observe(collect_pickup);
+}
+
+//==============================================================================================
+//        Loot Systems
+//==============================================================================================
+
+pub fn drop_loot(
+    trigger : Trigger<EnemyDied>,
+    mut commands : Commands,
+    enemies : Query<(&EnemyType, Option<&Elite>)>,
+    loot_tables : Res<LootTables>,
+    assets : Res<PickupAssets>,
+) {
+    let Ok((enemy_type, elite)) = enemies.get(trigger.target()) else { return };
+    let Some(table) = loot_tables.get(enemy_type) else { return };
+
+    let mut rng = rand::rng();
+    let bonus_rolls = elite.map(|elite| elite.affixes.len() as u32 * ELITE_BONUS_ROLLS_PER_AFFIX).unwrap_or(0);
+    for loot in table.roll(bonus_rolls) {
+        let scatter = Vec2::new(rng.random_range(-PICKUP_SCATTER..=PICKUP_SCATTER), rng.random_range(-PICKUP_SCATTER..=PICKUP_SCATTER));
+        let position = Vec3::new(trigger.position.x + scatter.x, PICKUP_HEIGHT, trigger.position.z + scatter.y);
+        spawn_pickup(&mut commands, &assets, loot, position);
+    }
+}
+
+/// Pulls every pickup inside of the pickup radius towards the player, speeding up as it goes.
+pub fn magnetize_pickups(
+    player : Single<&Transform, With<PlayerCharacter>>,
+    mut pickups : Query<(&mut Transform, &mut Pickup), Without<PlayerCharacter>>,
+    time : Res<Time>,
+) {
+    for (mut transform, mut pickup) in pickups.iter_mut() {
+        let offset = player.translation - transform.translation;
+        if offset.length() > PICKUP_RADIUS {
+            pickup.speed = 0.0;
+            continue;
+        }
+
+        pickup.speed = (pickup.speed + PICKUP_ACCELERATION * time.delta_secs()).min(PICKUP_MAX_SPEED);
+        transform.translation += offset.clamp_length_max(pickup.speed * time.delta_secs());
+    }
+}
+
+pub fn collect_pickup(
+    trigger : Trigger<OnCollisionStart>,
+    mut commands : Commands,
+    pickups : Query<&Pickup>,
+    players : Query<(), With<PlayerCharacter>>,
+) {
+    let Ok(pickup) = pickups.get(trigger.target()) else { return };
+    if !players.contains(trigger.collider) { return }
+
+    commands.trigger_targets(PickupCollected(pickup.loot), trigger.collider);
+    commands.entity(trigger.target()).despawn();
+}
+
+pub fn apply_pickup(
+    trigger : Trigger<PickupCollected>,
+    mut players : Query<(Option<&mut Health>, Option<&mut Mana>, Option<&mut Experience>, Option<&mut SpellScrolls>), With<PlayerCharacter>>,
+) {
+    let Ok((health, mana, experience, scrolls)) = players.get_mut(trigger.target()) else { return };
+    match trigger.0 {
+        Loot::Experience(amount) => if let Some(mut experience) = experience { experience.0 += amount },
+        Loot::Health(amount) => if let Some(mut health) = health { health.heal(amount) },
+        Loot::Mana(amount) => if let Some(mut mana) = mana { mana.restore(amount) },
+        Loot::SpellScroll => if let Some(mut scrolls) = scrolls { scrolls.0 += 1 },
+    }
+}
@@ -1,15 +1,21 @@
-use arena::{ArenaPlugin, Obstacle};
+use arena::{editor::ArenaEditorPlugin, ArenaPlugin, Obstacle};
 use assets::{AssetLoadingPlugin, WizardAssets};
 use bevy::prelude::*;
 use bevy_enhanced_input::EnhancedInputPlugin;
+use bevy_spatial::SpatialSet;
 use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
 use bevy_tnua::prelude::*;
 use bevy_tnua_avian3d::*;
+use benchmark::BenchmarkPlugin;
 use camera::CameraPlugin;
 use character::PlayerCharacterPlugin;
 use enemy::{EnemyPlugin, EnemyType, SpawnEnemiesEventBuilder};
 use render::{pixelate::PixelationEffect, RenderPhase};
 use spells::SpellPlugin;
+use steering::{LocalAvoidance, SteeringPlugin};
+use loot::LootPlugin;
+use pool::PoolingPlugin;
+use lod::{LodPlugin, LodSet};
 use avian3d::prelude::*;
 use vleue_navigator::prelude::*;
 
@@ -23,6 +29,11 @@ pub mod util;
 pub mod assets;
 pub mod spells;
 pub mod enemy;
+pub mod benchmark;
+pub mod steering;
+pub mod loot;
+pub mod pool;
+pub mod lod;
 
 //==============================================================================================
 //        GameState
@@ -32,10 +43,21 @@ pub mod enemy;
 pub enum GameState {
     #[default]
     Loading,
+    /// Waits for the arena file, see [`select_arena`](arena::definition::select_arena).
+    LoadingArena,
     InGame,
     GameOver,
 }
 
+/// How the last run ended, inserted on the way into [`GameState::GameOver`].
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// Every portal in the arena was closed.
+    Won,
+    /// The beacons fell, see [`LoseCondition`](arena::beacon::LoseCondition).
+    Lost,
+}
+
 //==============================================================================================
 //        Main Function
 //==============================================================================================
@@ -66,12 +88,24 @@ fn main() -> AppExit {
         //This has everything to do with the player character, including movement.
         .add_plugins(PlayerCharacterPlugin)
         
+        //Parks despawned enemies and projectiles so they can be reused.
+        .add_plugins(PoolingPlugin)
+        
         //This will have everything needed for the spells to work
         .add_plugins(SpellPlugin)
         
+        //Updates far away enemies less often, so large hordes stay cheap.
+        .add_plugins(LodPlugin)
+        
+        //Steering behaviours and local avoidance for anything that walks around in a crowd.
+        .add_plugins(SteeringPlugin)
+        
         //This is where all of the enemy logic is.
         .add_plugins(EnemyPlugin::new(cfg!(debug_assertions)))
         
+        //Drops loot from dead enemies and lets the player pick it up.
+        .add_plugins(LootPlugin)
+        
         //This plugin is a helper that will set the default animation for a scene after it is loaded.
         .add_plugins(DefaultSceneAnimationPlugin)
         
@@ -83,6 +117,11 @@ fn main() -> AppExit {
     if cfg!(feature="native") {
         app.add_plugins(PixelationEffect::plugin());
     }
+    
+    // Spawns a large horde and reports frame times, run with `--features benchmark --release`
+    if cfg!(feature="benchmark") {
+        app.add_plugins(BenchmarkPlugin);
+    }
 
     
     // All plugins that are only used in non release builds
@@ -92,11 +131,12 @@ fn main() -> AppExit {
             .add_plugins(WorldInspectorPlugin::new())
             .add_plugins(PhysicsDebugPlugin::default())
             .insert_gizmo_config(PhysicsGizmos::default(), GizmoConfig::default())
+            .add_plugins(ArenaEditorPlugin)
         ;
     }
     
     app.configure_sets(OnEnter(GameState::InGame), (GameInit, PostGameInit).chain());
-    app.configure_sets(Update, (DefaultEnemyBehavior, SpecialEnemyBehavior).chain().run_if(in_state(GameState::InGame)));
+    app.configure_sets(Update, (SpatialSet, LodSet, DefaultEnemyBehavior, SpecialEnemyBehavior, LocalAvoidance).chain().run_if(in_state(GameState::InGame)));
     
     app.run()
 }
@@ -105,19 +145,6 @@ fn main() -> AppExit {
 fn setup(
     mut commands: Commands,
 ) {
-    let directional_light = DirectionalLight {
-        color: Color::Srgba(Srgba::rgba_u8(138, 135, 245, 255)),
-        shadows_enabled: true,
-        ..Default::default()
-    };
-    
-    // light
-    commands.spawn((
-        directional_light,
-        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, 5.5, 1.0, 0.0))
-    ));
-    
-    
     commands.trigger(SpawnEnemiesEventBuilder::new((-15.0, 0.0, -15.0).into())
         .with_weight(EnemyType::Minion, 1)
         .with_number_of_enemies(4)
@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+//==============================================================================================
+//        Pooling Plugin
+//==============================================================================================
+
+/// Keeps the colliders of parked entities, and of everything under them, out of the physics.
+pub struct PoolingPlugin;
+
+impl Plugin for PoolingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_observer(disable_inactive_colliders)
+            .add_observer(enable_active_colliders)
+        ;
+    }
+}
+
+/// A pool of parked entities that all have the component `T`. Anything that spawns a `T` should
+/// try to [`take`](EntityPool::take) one from here first.
+pub struct EntityPoolPlugin<T : Component>(PhantomData<T>);
+
+impl<T : Component> Default for EntityPoolPlugin<T> {
+    fn default() -> Self {
+        EntityPoolPlugin(PhantomData)
+    }
+}
+
+impl<T : Component> Plugin for EntityPoolPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EntityPool<T>>()
+            .add_observer(release_to_pool::<T>)
+        ;
+    }
+}
+
+//==============================================================================================
+//        Pool Components
+//==============================================================================================
+
+/// Marks an entity that goes back into its pool instead of being despawned.
+#[derive(Component)]
+pub struct Pooled;
+
+/// A parked entity. It is hidden, its physics are disabled and it is waiting in a pool to be reused.
+#[derive(Component)]
+pub struct Inactive;
+
+/// Triggered on a [`Pooled`] entity to park it in the pool it belongs to.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ReleaseToPool;
+
+#[derive(Resource)]
+pub struct EntityPool<T : Component> {
+    free : Vec<Entity>,
+    _marker : PhantomData<T>,
+}
+
+impl<T : Component> Default for EntityPool<T> {
+    fn default() -> Self {
+        EntityPool { free: Vec::new(), _marker: PhantomData }
+    }
+}
+
+impl<T : Component> EntityPool<T> {
+    /// Takes a parked entity out of the pool. It is still inactive, call [`activate`] once it
+    /// has been reset.
+    pub fn take(&mut self) -> Option<Entity> {
+        self.free.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
+
+/// Despawns the entity, or parks it if it is [`Pooled`].
+pub fn despawn_or_release(commands : &mut Commands, entity : Entity, pooled : bool) {
+    if pooled {
+        commands.trigger_targets(ReleaseToPool, entity);
+    } else {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Brings a parked entity back into the world.
+pub fn activate(commands : &mut Commands, entity : Entity) {
+    commands.entity(entity)
+        .remove::<(Inactive, RigidBodyDisabled, ColliderDisabled)>()
+        .insert(Visibility::Inherited);
+}
+
+//==============================================================================================
+//        Pool Systems
+//==============================================================================================
+
+pub fn release_to_pool<T : Component>(
+    trigger : Trigger<ReleaseToPool>,
+    mut commands : Commands,
+    entities : Query<(), (With<T>, With<Pooled>, Without<Inactive>)>,
+    mut pool : ResMut<EntityPool<T>>,
+) {
+    let entity = trigger.target();
+    if !entities.contains(entity) { return }
+
+    commands.entity(entity).insert((
+        Inactive,
+        Visibility::Hidden,
+        RigidBodyDisabled,
+        ColliderDisabled,
+        LinearVelocity::ZERO,
+        AngularVelocity::ZERO,
+    ));
+    pool.free.push(entity);
+}
+
+pub fn disable_inactive_colliders(
+    trigger : Trigger<OnAdd, Inactive>,
+    mut commands : Commands,
+    children : Query<&Children>,
+    colliders : Query<(), With<Collider>>,
+) {
+    for child in children.iter_descendants(trigger.target()).filter(|child| colliders.contains(*child)) {
+        commands.entity(child).insert(ColliderDisabled);
+    }
+}
+
+pub fn enable_active_colliders(
+    trigger : Trigger<OnRemove, Inactive>,
+    mut commands : Commands,
+    children : Query<&Children>,
+    colliders : Query<(), With<Collider>>,
+) {
+    for child in children.iter_descendants(trigger.target()).filter(|child| colliders.contains(*child)) {
+        commands.entity(child).remove::<ColliderDisabled>();
+    }
+}
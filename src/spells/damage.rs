@@ -1,8 +1,9 @@
-
-use avian3d::prelude::OnCollisionStart;
+use avian3d::prelude::{Collider, LayerMask, OnCollisionStart, SpatialQuery, SpatialQueryFilter};
 use bevy::prelude::*;
 
-use crate::util::Health;
+use crate::{enemy::perception::Noise, pool::{despawn_or_release, Pooled}, util::Health};
+
+const SPELL_IMPACT_NOISE: f32 = 6.0;
 
 //==============================================================================================
 //        DamageBox Plugin
@@ -12,7 +13,110 @@ pub struct DamageBoxPlugin;
 
 impl Plugin for DamageBoxPlugin {
     fn build(&self, app: &mut App) {
-        todo!()
+        app
+            .add_observer(apply_damage)
+            
+            .add_systems(Update, (recharge_shields, detonate_damage_volumes))
+        ;
+    }
+}
+
+//==============================================================================================
+//        Damage Event
+//==============================================================================================
+
+/// Triggered on the entity that should take damage. Every source of damage should go through
+/// this event so that anything reacting to damage only has to listen in one place.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Damage {
+    pub amount : f32,
+    pub source : Option<Entity>,
+}
+
+impl Damage {
+    pub fn new(amount : f32) -> Self {
+        Damage { amount, source: None }
+    }
+    
+    pub fn from_source(amount : f32, source : Entity) -> Self {
+        Damage { amount, source: Some(source) }
+    }
+}
+
+pub fn apply_damage(
+    trigger : Trigger<Damage>,
+    mut target : Query<(&mut Health, Option<&Armor>, Option<&mut Shield>)>,
+) {
+    let Ok((mut health, armor, shield)) = target.get_mut(trigger.target()) else { return };
+    let mut amount = trigger.amount * (1.0 - armor.map(|armor| armor.0.clamp(0.0, 1.0)).unwrap_or(0.0));
+    if let Some(mut shield) = shield {
+        amount = shield.absorb(amount);
+    }
+    health.take_damage(amount);
+}
+
+//==============================================================================================
+//        Damage Modifiers
+//==============================================================================================
+
+/// The fraction of all incoming damage that is ignored.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Armor(pub f32);
+
+/// Soaks up damage before it reaches the health. Recharges once nothing has hit it for a while.
+#[derive(Component, Debug)]
+pub struct Shield {
+    pub max : f32,
+    pub current : f32,
+    pub recharge_rate : f32,
+    pub recharge_delay : Timer,
+}
+
+impl Shield {
+    pub fn new(max : f32, recharge_rate : f32, recharge_delay : f32) -> Self {
+        Shield {
+            max,
+            current : max,
+            recharge_rate,
+            recharge_delay : Timer::from_seconds(recharge_delay, TimerMode::Once),
+        }
+    }
+    
+    /// Takes as much of the damage as the shield has left and returns whatever goes through.
+    pub fn absorb(&mut self, amount : f32) -> f32 {
+        let absorbed = amount.min(self.current);
+        self.current -= absorbed;
+        self.recharge_delay.reset();
+        amount - absorbed
+    }
+}
+
+pub fn recharge_shields(
+    mut shields : Query<&mut Shield>,
+    time : Res<Time>,
+) {
+    for mut shield in shields.iter_mut() {
+        shield.recharge_delay.tick(time.delta());
+        if !shield.recharge_delay.finished() { continue; }
+        shield.current = (shield.current + shield.recharge_rate * time.delta_secs()).min(shield.max);
+    }
+}
+
+/// Blocks every spell that hits the front of the entity. Only spells are stopped, anything that
+/// deals damage directly still goes through.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FrontShield {
+    /// The full angle of the covered arc in radians, centered on the facing of the entity.
+    pub arc : f32,
+}
+
+impl FrontShield {
+    /// Whether a spell travelling along `direction` comes in from the covered arc.
+    pub fn blocks(&self, facing : Vec3, direction : Vec3) -> bool {
+        let facing = facing.xz().normalize_or_zero();
+        let incoming = -direction.xz().normalize_or_zero();
+        if facing == Vec2::ZERO || incoming == Vec2::ZERO { return false }
+        facing.angle_to(incoming).abs() <= self.arc * 0.5
     }
 }
 
@@ -36,18 +140,87 @@ pub struct DestroyOnSpellDamage;
 pub fn apply_spell_damage(
     trigger : Trigger<OnCollisionStart>,
     mut commands : Commands,
-    mut target : Query<&mut Health>,
-    spells : Query<(Entity, &SpellDamage, Option<&DestroyOnSpellDamage>)>
+    target : Query<(&GlobalTransform, Option<&FrontShield>), With<Health>>,
+    spells : Query<(Entity, &SpellDamage, &GlobalTransform, Option<&DestroyOnSpellDamage>, Has<Pooled>)>,
+    mut noises : EventWriter<Noise>,
 ) -> Result<(), BevyError> {
-    let (entity, spell_damage, destroy_on_spell_damage) = spells.get(trigger.target())?;
-    let mut enemy = target.get_mut(trigger.collider)?;
+    let (entity, spell_damage, transform, destroy_on_spell_damage, pooled) = spells.get(trigger.target())?;
+    let (target_transform, front_shield) = target.get(trigger.collider)?;
     
-    enemy.take_damage(spell_damage.0);
+    // A blocked spell is still used up, it just never gets to deal its damage.
+    let blocked = front_shield.is_some_and(|shield| shield.blocks(*target_transform.forward(), *transform.forward()));
+    if !blocked {
+        commands.trigger_targets(Damage::from_source(spell_damage.0, entity), trigger.collider);
+    }
+    noises.write(Noise::new(transform.translation(), SPELL_IMPACT_NOISE, entity));
     
-    if destroy_on_spell_damage.is_some() { commands.entity(entity).despawn(); };
+    if destroy_on_spell_damage.is_some() { despawn_or_release(&mut commands, entity, pooled); };
     Ok(())
 }
 
-pub fn spawn_damage_box(commands : &mut Commands, ) {
+//==============================================================================================
+//        Damage Volumes
+//==============================================================================================
+
+/// Deals its damage once to everything inside of its shape after the telegraph runs out, then
+/// despawns along with whatever was attached to it to show the telegraph.
+#[derive(Component)]
+#[require(Transform)]
+pub struct DamageVolume {
+    pub damage : f32,
+    pub source : Option<Entity>,
+    pub shape : Collider,
+    pub targets : LayerMask,
+    pub telegraph : Timer,
+}
+
+impl DamageVolume {
+    pub fn new(damage : f32, shape : Collider, targets : impl Into<LayerMask>, telegraph : f32) -> Self {
+        DamageVolume {
+            damage,
+            source : None,
+            shape,
+            targets : targets.into(),
+            telegraph : Timer::from_seconds(telegraph, TimerMode::Once),
+        }
+    }
     
-}
\ No newline at end of file
+    pub fn with_source(mut self, source : Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+pub fn spawn_damage_box<'a>(commands : &'a mut Commands, volume : DamageVolume, transform : Transform) -> EntityCommands<'a> {
+    commands.spawn((
+        Name::new("DamageVolume"),
+        volume,
+        transform,
+        Visibility::default(),
+    ))
+}
+
+pub fn detonate_damage_volumes(
+    mut commands : Commands,
+    mut volumes : Query<(Entity, &Transform, &mut DamageVolume)>,
+    targets : Query<(), With<Health>>,
+    spatial_query : SpatialQuery,
+    time : Res<Time>,
+) {
+    for (entity, transform, mut volume) in volumes.iter_mut() {
+        volume.telegraph.tick(time.delta());
+        if !volume.telegraph.finished() { continue; }
+        
+        let hits = spatial_query.shape_intersections(
+            &volume.shape,
+            transform.translation,
+            transform.rotation,
+            &SpatialQueryFilter::from_mask(volume.targets),
+        );
+        for hit in hits.into_iter().filter(|hit| targets.contains(*hit)) {
+            let damage = Damage { amount: volume.damage, source: volume.source };
+            commands.trigger_targets(damage, hit);
+        }
+        commands.entity(entity).despawn();
+    }
+}
@@ -2,9 +2,9 @@ use std::{f32::consts::PI, marker::PhantomData};
 
 use avian3d::{position, prelude::OnCollisionStart, sync};
 use bevy::{ecs::system::IntoObserverSystem, prelude::*, render::render_resource::ShaderSize};
-use phantom_blade::{phantom_blade_spell_effect, PhantomBlade, PHANTOM_BLADE_COOLDOWN};
+use phantom_blade::{PhantomBlade, PhantomBladePlugin, PHANTOM_BLADE_COOLDOWN};
 
-use crate::{assets::SpellAssets, enemy::Enemy, spells::damage::SpellDamage};
+use crate::{assets::SpellAssets, enemy::{perception::Noise, Enemy}, spells::damage::{DamageBoxPlugin, SpellDamage}};
 
 pub mod phantom_blade;
 pub mod damage;
@@ -18,12 +18,14 @@ pub struct SpellPlugin;
 impl Plugin for SpellPlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_plugins(DamageBoxPlugin)
+            .add_plugins(PhantomBladePlugin)
+            
             .init_resource::<Spellbook>()
             
             .add_observer(cast_spell)
             
             .add_systems(PreUpdate, tick_spellbook_cooldown)
-            .add_systems(Update, phantom_blade_spell_effect)
         ;
     }
 }
@@ -33,6 +35,7 @@ impl Plugin for SpellPlugin {
 //==============================================================================================
 
 const NUMBER_OF_SPELLS: usize = 3;
+const SPELL_CAST_NOISE: f32 = 10.0;
 
 #[derive(Resource)]
 pub struct Spellbook {
@@ -50,17 +53,22 @@ impl Default for Spellbook {
 }
 
 pub trait Spell {
-    fn cast(&self, commands : Commands, transform : Transform, spell_assets : &SpellAssets);
+    fn cast(&self, commands : Commands, transform : Transform, caster : Entity, spell_assets : &SpellAssets);
     
     fn cooldown(&self) -> f32;
 }
 
+/// Whoever cast the spell. Enemies hold the damage the spell deals against them.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Caster(pub Entity);
+
 //==============================================================================================
 //        Spell Systems
 //==============================================================================================
 
 #[derive(Event)]
 pub struct CastSpell{
+    pub caster : Entity,
     pub position : Vec3,
     pub direction : Vec2,
     pub spell_index : usize
@@ -71,6 +79,7 @@ fn cast_spell(
     commands : Commands,
     spell_assets : Res<SpellAssets>,
     mut spellbook : ResMut<Spellbook>,
+    mut noises : EventWriter<Noise>,
     mut gizmos : Gizmos
 ) {
     let Some(Some(spell)) = spellbook.spells.get(trigger.spell_index) else {
@@ -79,8 +88,9 @@ fn cast_spell(
     let direction = trigger.direction;
     let angle = -direction.to_angle();
     let transform = Transform::from_translation(trigger.position).with_rotation(Quat::from_rotation_y(angle - PI / 2.0));
-    spell.cast(commands, transform, spell_assets.as_ref());
+    spell.cast(commands, transform, trigger.caster, spell_assets.as_ref());
     spellbook.cooldown.reset();
+    noises.write(Noise::new(trigger.position, SPELL_CAST_NOISE, trigger.caster));
 }
 
 fn tick_spellbook_cooldown(
@@ -1,4 +1,4 @@
-use crate::{assets::SpellAssets, enemy::Enemy, spells::{damage::{apply_spell_damage, DestroyOnSpellDamage}, SpellDamage}, util::{player_spell_layer, GameCollisionLayer}};
+use crate::{assets::SpellAssets, enemy::Enemy, pool::{activate, EntityPool, EntityPoolPlugin, Inactive, Pooled, ReleaseToPool}, spells::{damage::{apply_spell_damage, DestroyOnSpellDamage}, Caster, SpellDamage}, util::{player_spell_layer, GameCollisionLayer}};
 
 use super::Spell;
 use avian3d::prelude::{Collider, CollisionEventsEnabled, CollisionLayers, CollisionStarted, OnCollisionStart, RigidBody, Sensor};
@@ -16,6 +16,8 @@ pub struct PhantomBladePlugin;
 impl Plugin for PhantomBladePlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_plugins(EntityPoolPlugin::<PhantomBladeSpellEffect>::default())
+            
             .add_systems(Update, phantom_blade_spell_effect)
         ;
     }
@@ -28,31 +30,38 @@ impl Plugin for PhantomBladePlugin {
 pub struct PhantomBlade;
 
 impl Spell for PhantomBlade {
-    fn cast(&self, mut commands : Commands, transform : Transform, spell_assets : &SpellAssets) {
-        commands.spawn((
-            transform,
-            InheritedVisibility::default(),
-            PhantomBladeSpellEffect::default(),
-            children![
-                (
-                    Transform::from_rotation(Quat::from_rotation_x(-90.0_f32.to_radians())),
-                    Sensor,
-                    Collider::cylinder(0.1, 1.0),
-                    InheritedVisibility::default(),
-                    CollisionEventsEnabled,
-                    Observer::new(apply_spell_damage),
-                    SpellDamage(PHANTOM_BLADE_DAMAGE),
-                    DestroyOnSpellDamage,
-                    player_spell_layer(),
-                    children![
-                        (
-                            Transform::from_translation(Vec3::new(0.0, -0.5, 0.0)),
-                            SceneRoot(spell_assets.dagger.clone()),
-                        )
-                    ],
-                )
-            ]
-        ));
+    fn cast(&self, mut commands : Commands, transform : Transform, caster : Entity, spell_assets : &SpellAssets) {
+        let dagger = spell_assets.dagger.clone();
+        commands.queue(move |world : &mut World| {
+            if let Some(entity) = world.resource_mut::<EntityPool<PhantomBladeSpellEffect>>().take() {
+                world.entity_mut(entity).insert((transform, Caster(caster), PhantomBladeSpellEffect::default()));
+                activate(&mut world.commands(), entity);
+                world.flush();
+                return;
+            }
+            
+            let blade_rotation = Quat::from_rotation_x(-90.0_f32.to_radians());
+            world.spawn((
+                Name::new("Phantom Blade"),
+                transform,
+                Visibility::default(),
+                PhantomBladeSpellEffect::default(),
+                Caster(caster),
+                Pooled,
+                Sensor,
+                Collider::compound(vec![(Vec3::ZERO, blade_rotation, Collider::cylinder(0.1, 1.0))]),
+                CollisionEventsEnabled,
+                SpellDamage(PHANTOM_BLADE_DAMAGE),
+                DestroyOnSpellDamage,
+                player_spell_layer(),
+                children![
+                    (
+                        Transform::from_rotation(blade_rotation) * Transform::from_xyz(0.0, -0.5, 0.0),
+                        SceneRoot(dagger),
+                    )
+                ],
+            )).observe(apply_spell_damage);
+        });
     }
 
     fn cooldown(&self) -> f32 {
@@ -76,7 +85,7 @@ impl Default for PhantomBladeSpellEffect {
 
 pub fn phantom_blade_spell_effect(
     mut commands : Commands,
-    mut daggers : Query<(Entity, &mut Transform, &mut PhantomBladeSpellEffect), With<PhantomBladeSpellEffect>>,
+    mut daggers : Query<(Entity, &mut Transform, &mut PhantomBladeSpellEffect), Without<Inactive>>,
     time : Res<Time>
 ) {
     for (entity, mut transform, mut effect) in daggers.iter_mut() {
@@ -85,7 +94,7 @@ pub fn phantom_blade_spell_effect(
         
         effect.0.tick(time.delta());
         if effect.0.finished() {
-            commands.entity(entity).despawn();
+            commands.trigger_targets(ReleaseToPool, entity);
         }
     }
 }
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_spatial::{kdtree::KDTree3, AutomaticUpdate, SpatialAccess, SpatialStructure};
+use orca::{orca_line, solve_orca, OrcaNeighbour};
+
+use crate::{lod::{Lod, LodFrame}, util::{vec2_vec3, GameCollisionLayer}};
+
+pub mod orca;
+
+/// How often the steering kd-tree is rebuilt. Anything shorter than a frame rebuilds it every frame.
+const STEERING_TREE_REFRESH : Duration = Duration::from_millis(1);
+const ORCA_TIME_HORIZON : f32 = 1.0;
+const ORCA_NEIGHBOUR_RADIUS : f32 = 3.0;
+const ORCA_MAX_NEIGHBOURS : usize = 10;
+
+//==============================================================================================
+//        Steering Plugin
+//==============================================================================================
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(AutomaticUpdate::<SteeringAgent>::new()
+                .with_spatial_ds(SpatialStructure::KDTree3)
+                .with_frequency(STEERING_TREE_REFRESH)
+            )
+
+            .add_systems(Update, compute_local_avoidance.in_set(LocalAvoidance))
+        ;
+    }
+}
+
+/// Runs after every behaviour has written its preferred velocity, and turns those into velocities
+/// that don't run into other agents. Anything that moves an agent should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocalAvoidance;
+
+//==============================================================================================
+//        Steering Agent
+//==============================================================================================
+
+/// Anything that walks around with other agents. Behaviours only ever set the preferred velocity,
+/// the velocity that is actually used comes out of the local avoidance in [`AvoidanceVelocity`].
+#[derive(Component, Debug)]
+#[require(AvoidanceVelocity)]
+pub struct SteeringAgent {
+    pub radius : f32,
+    pub max_speed : f32,
+    pub preferred_velocity : Vec2,
+    /// Where the agent should look, when `None` it looks where it is going.
+    pub facing : Option<Dir3>,
+}
+
+impl SteeringAgent {
+    pub fn new(radius : f32, max_speed : f32) -> Self {
+        SteeringAgent {
+            radius,
+            max_speed,
+            preferred_velocity : Vec2::ZERO,
+            facing : None,
+        }
+    }
+
+    pub fn steer(&mut self, preferred_velocity : Vec2) {
+        self.preferred_velocity = preferred_velocity.clamp_length_max(self.max_speed);
+        self.facing = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.preferred_velocity = Vec2::ZERO;
+        self.facing = None;
+    }
+
+    pub fn face(&mut self, direction : Vec2) {
+        self.facing = Dir3::new(vec2_vec3(direction)).ok();
+    }
+
+    /// The direction the agent should be looking in, given the velocity it ended up with.
+    pub fn forward(&self, velocity : Vec2) -> Option<Dir3> {
+        self.facing.or_else(|| Dir3::new(vec2_vec3(velocity)).ok())
+    }
+}
+
+/// The velocity of an agent after local avoidance.
+#[derive(Component, Debug, Default, Deref)]
+pub struct AvoidanceVelocity(pub Vec2);
+
+/// Every steering agent is tracked in this kd-tree, use it for any neighbour lookups between agents.
+pub type SteeringTree = KDTree3<SteeringAgent>;
+
+//==============================================================================================
+//        Steering Behaviours
+//==============================================================================================
+
+/// Full speed towards the target.
+pub fn seek(position : Vec2, target : Vec2, max_speed : f32) -> Vec2 {
+    (target - position).normalize_or_zero() * max_speed
+}
+
+/// Full speed away from the threat.
+pub fn flee(position : Vec2, threat : Vec2, max_speed : f32) -> Vec2 {
+    (position - threat).normalize_or_zero() * max_speed
+}
+
+/// Like [`seek`], but slows down inside of the slowing radius so the target isn't overshot.
+pub fn arrive(position : Vec2, target : Vec2, max_speed : f32, slowing_radius : f32) -> Vec2 {
+    let offset = target - position;
+    let distance = offset.length();
+    if distance <= f32::EPSILON { return Vec2::ZERO }
+    let speed = if distance < slowing_radius { max_speed * distance / slowing_radius } else { max_speed };
+    offset / distance * speed
+}
+
+/// Pushes away from every neighbour, the closer the neighbour the harder the push.
+pub fn separation(position : Vec2, neighbours : impl IntoIterator<Item = Vec2>, radius : f32) -> Vec2 {
+    let mut count = 0;
+    let mut push = Vec2::ZERO;
+    for neighbour in neighbours {
+        let distance = position.distance(neighbour);
+        if distance <= 0.0 || distance >= radius { continue; }
+        push += (position - neighbour).normalize_or_zero() / distance;
+        count += 1;
+    }
+    if count > 0 { push / count as f32 } else { Vec2::ZERO }
+}
+
+/// The average heading of the neighbours.
+pub fn alignment(neighbour_velocities : impl IntoIterator<Item = Vec2>) -> Vec2 {
+    let mut count = 0;
+    let mut heading = Vec2::ZERO;
+    for velocity in neighbour_velocities {
+        heading += velocity;
+        count += 1;
+    }
+    if count > 0 { heading / count as f32 } else { Vec2::ZERO }
+}
+
+/// Casts a ray ahead of the agent and steers along the surface of any obstacle it would run into.
+pub fn obstacle_avoidance(
+    spatial_query : &SpatialQuery,
+    position : Vec3,
+    velocity : Vec2,
+    look_ahead : f32,
+    filter : &SpatialQueryFilter,
+) -> Vec2 {
+    let Ok(direction) = Dir3::new(vec2_vec3(velocity)) else { return Vec2::ZERO };
+    let Some(hit) = spatial_query.cast_ray(position, direction, look_ahead, true, filter) else { return Vec2::ZERO };
+
+    let normal = hit.normal.xz().normalize_or_zero();
+    let urgency = 1.0 - hit.distance / look_ahead;
+    // Slide along the obstacle instead of bouncing straight back off of it.
+    let tangent = velocity - normal * velocity.dot(normal);
+    (normal + tangent.normalize_or_zero()) * urgency * velocity.length()
+}
+
+/// The filter used by [`obstacle_avoidance`] to only look for obstacles.
+pub fn obstacle_filter() -> SpatialQueryFilter {
+    SpatialQueryFilter::from_mask(GameCollisionLayer::Obstacle)
+}
+
+//==============================================================================================
+//        Local Avoidance
+//==============================================================================================
+
+pub fn compute_local_avoidance(
+    mut agents : Query<(Entity, &Transform, &SteeringAgent, Option<&LinearVelocity>, &mut AvoidanceVelocity, Option<&Lod>)>,
+    neighbours : Query<(&Transform, &SteeringAgent, Option<&LinearVelocity>)>,
+    steering_tree : Res<SteeringTree>,
+    time : Res<Time>,
+    lod_frame : Res<LodFrame>,
+) {
+    let delta_secs = time.delta_secs();
+
+    agents.par_iter_mut().for_each(|(entity, transform, agent, velocity, mut avoidance_velocity, lod)| {
+        if lod.is_some_and(|lod| !lod_frame.should_update(entity, lod)) { return };
+        let position = transform.translation.xz();
+        let velocity = velocity.map(|velocity| velocity.xz()).unwrap_or(agent.preferred_velocity);
+
+        let mut nearby = steering_tree.within_distance(transform.translation, ORCA_NEIGHBOUR_RADIUS + agent.radius);
+        nearby.sort_by(|a, b| a.0.distance_squared(transform.translation).total_cmp(&b.0.distance_squared(transform.translation)));
+
+        let lines = nearby.into_iter()
+            .filter_map(|(_, other)| other.filter(|other| *other != entity))
+            .filter_map(|other| neighbours.get(other).ok())
+            .take(ORCA_MAX_NEIGHBOURS)
+            .map(|(other_transform, other_agent, other_velocity)| OrcaNeighbour {
+                position : other_transform.translation.xz(),
+                velocity : other_velocity.map(|velocity| velocity.xz()).unwrap_or(other_agent.preferred_velocity),
+                radius : other_agent.radius,
+            })
+            .map(|neighbour| orca_line(position, velocity, agent.radius, &neighbour, ORCA_TIME_HORIZON, delta_secs))
+            .collect::<Vec<_>>();
+
+        avoidance_velocity.0 = solve_orca(&lines, agent.preferred_velocity, agent.max_speed);
+    });
+}
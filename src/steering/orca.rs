@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+
+const ORCA_EPSILON: f32 = 0.00001;
+
+//==============================================================================================
+//        ORCA Lines
+//==============================================================================================
+
+/// A half plane of permitted velocities. Everything to the left of `direction` going through
+/// `point` is allowed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrcaLine {
+    pub point : Vec2,
+    pub direction : Vec2,
+}
+
+/// Another agent as seen by the agent doing the avoiding.
+#[derive(Clone, Copy, Debug)]
+pub struct OrcaNeighbour {
+    pub position : Vec2,
+    pub velocity : Vec2,
+    pub radius : f32,
+}
+
+/// Builds the ORCA half plane for a single neighbour. Both agents are expected to take half of the
+/// responsibility for avoiding each other, which is what makes the avoidance reciprocal.
+pub fn orca_line(
+    position : Vec2,
+    velocity : Vec2,
+    radius : f32,
+    neighbour : &OrcaNeighbour,
+    time_horizon : f32,
+    delta_secs : f32,
+) -> OrcaLine {
+    let relative_position = neighbour.position - position;
+    let relative_velocity = velocity - neighbour.velocity;
+    let distance_squared = relative_position.length_squared();
+    let combined_radius = radius + neighbour.radius;
+    let combined_radius_squared = combined_radius * combined_radius;
+    let inverse_time_horizon = 1.0 / time_horizon;
+
+    let (direction, u) = if distance_squared > combined_radius_squared {
+        // No collision yet, find the closest point on the truncated velocity obstacle.
+        let w = relative_velocity - inverse_time_horizon * relative_position;
+        let w_length_squared = w.length_squared();
+        let dot_product = w.dot(relative_position);
+
+        if dot_product < 0.0 && dot_product * dot_product > combined_radius_squared * w_length_squared {
+            // Project on the cut-off circle.
+            let w_length = w_length_squared.sqrt();
+            let unit_w = w / w_length;
+            (Vec2::new(unit_w.y, -unit_w.x), (combined_radius * inverse_time_horizon - w_length) * unit_w)
+        } else {
+            // Project on one of the legs.
+            let leg = (distance_squared - combined_radius_squared).sqrt();
+            let direction = if relative_position.perp_dot(w) > 0.0 {
+                Vec2::new(
+                    relative_position.x * leg - relative_position.y * combined_radius,
+                    relative_position.x * combined_radius + relative_position.y * leg,
+                ) / distance_squared
+            } else {
+                -Vec2::new(
+                    relative_position.x * leg + relative_position.y * combined_radius,
+                    -relative_position.x * combined_radius + relative_position.y * leg,
+                ) / distance_squared
+            };
+            let dot_product = relative_velocity.dot(direction);
+            (direction, dot_product * direction - relative_velocity)
+        }
+    } else {
+        // Already colliding, push apart within a single frame.
+        let inverse_time_step = 1.0 / delta_secs.max(ORCA_EPSILON);
+        let w = relative_velocity - inverse_time_step * relative_position;
+        let w_length = w.length().max(ORCA_EPSILON);
+        let unit_w = w / w_length;
+        (Vec2::new(unit_w.y, -unit_w.x), (combined_radius * inverse_time_step - w_length) * unit_w)
+    };
+
+    OrcaLine {
+        point : velocity + 0.5 * u,
+        direction,
+    }
+}
+
+//==============================================================================================
+//        ORCA Solver
+//==============================================================================================
+
+/// Finds the velocity closest to `preferred_velocity` that satisfies every line and does not go
+/// over `max_speed`. When the lines can't all be satisfied, the velocity that violates them the
+/// least is returned instead.
+pub fn solve_orca(lines : &[OrcaLine], preferred_velocity : Vec2, max_speed : f32) -> Vec2 {
+    let mut result = Vec2::ZERO;
+    let failed_line = linear_program_2(lines, max_speed, preferred_velocity, false, &mut result);
+    if failed_line < lines.len() {
+        linear_program_3(lines, failed_line, max_speed, &mut result);
+    }
+    result
+}
+
+fn linear_program_1(
+    lines : &[OrcaLine],
+    line_index : usize,
+    radius : f32,
+    optimal_velocity : Vec2,
+    optimize_direction : bool,
+    result : &mut Vec2,
+) -> bool {
+    let line = lines[line_index];
+    let dot_product = line.point.dot(line.direction);
+    let discriminant = dot_product * dot_product + radius * radius - line.point.length_squared();
+
+    // The max speed circle fully invalidates this line.
+    if discriminant < 0.0 { return false }
+
+    let discriminant_root = discriminant.sqrt();
+    let mut t_left = -dot_product - discriminant_root;
+    let mut t_right = -dot_product + discriminant_root;
+
+    for other in &lines[..line_index] {
+        let denominator = line.direction.perp_dot(other.direction);
+        let numerator = other.direction.perp_dot(line.point - other.point);
+
+        if denominator.abs() <= ORCA_EPSILON {
+            // The lines are (almost) parallel.
+            if numerator < 0.0 { return false }
+            continue;
+        }
+
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+
+        if t_left > t_right { return false }
+    }
+
+    *result = if optimize_direction {
+        if optimal_velocity.dot(line.direction) > 0.0 {
+            line.point + t_right * line.direction
+        } else {
+            line.point + t_left * line.direction
+        }
+    } else {
+        let t = line.direction.dot(optimal_velocity - line.point);
+        line.point + t.clamp(t_left, t_right) * line.direction
+    };
+
+    true
+}
+
+fn linear_program_2(
+    lines : &[OrcaLine],
+    radius : f32,
+    optimal_velocity : Vec2,
+    optimize_direction : bool,
+    result : &mut Vec2,
+) -> usize {
+    *result = if optimize_direction {
+        optimal_velocity * radius
+    } else if optimal_velocity.length_squared() > radius * radius {
+        optimal_velocity.normalize_or_zero() * radius
+    } else {
+        optimal_velocity
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.direction.perp_dot(line.point - *result) > 0.0 {
+            let previous = *result;
+            if !linear_program_1(lines, index, radius, optimal_velocity, optimize_direction, result) {
+                *result = previous;
+                return index;
+            }
+        }
+    }
+
+    lines.len()
+}
+
+fn linear_program_3(
+    lines : &[OrcaLine],
+    begin_line : usize,
+    radius : f32,
+    result : &mut Vec2,
+) {
+    let mut distance = 0.0;
+
+    for (index, line) in lines.iter().enumerate().skip(begin_line) {
+        if line.direction.perp_dot(line.point - *result) <= distance { continue; }
+
+        let mut projected_lines = Vec::with_capacity(index);
+        for other in &lines[..index] {
+            let determinant = line.direction.perp_dot(other.direction);
+            let point = if determinant.abs() <= ORCA_EPSILON {
+                if line.direction.dot(other.direction) > 0.0 { continue; }
+                0.5 * (line.point + other.point)
+            } else {
+                line.point + (other.direction.perp_dot(line.point - other.point) / determinant) * line.direction
+            };
+
+            projected_lines.push(OrcaLine {
+                point,
+                direction : (other.direction - line.direction).normalize_or_zero(),
+            });
+        }
+
+        let previous = *result;
+        let optimal_direction = Vec2::new(-line.direction.y, line.direction.x);
+        if linear_program_2(&projected_lines, radius, optimal_direction, true, result) < projected_lines.len() {
+            // This should in principle not happen, the result is by definition already in the
+            // feasible region of this linear program. If it fails it is due to small floating point errors.
+            *result = previous;
+        }
+
+        distance = line.direction.perp_dot(line.point - *result);
+    }
+}
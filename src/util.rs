@@ -1,7 +1,7 @@
 
 use std::collections::HashMap;
 
-use avian3d::prelude::{CollisionLayers, PhysicsLayer};
+use avian3d::prelude::{CollisionLayers, LayerMask, PhysicsLayer};
 use bevy::{prelude::*, scene::{InstanceId, SceneInstanceReady}};
 
 //==============================================================================================
@@ -25,7 +25,9 @@ pub enum GameCollisionLayer {
     Player,
     Enemy,
     Spell,
-    Obstacle
+    Obstacle,
+    Pickup,
+    Barrier,
 }
 
 pub fn player_spell_layer() -> CollisionLayers {
@@ -36,6 +38,10 @@ pub fn enemy_spell_layer() -> CollisionLayers {
     CollisionLayers::new(GameCollisionLayer::Spell, GameCollisionLayer::Player)
 }
 
+pub fn obstacle_layer() -> CollisionLayers {
+    CollisionLayers::new(GameCollisionLayer::Obstacle, LayerMask::ALL)
+}
+
 //==============================================================================================
 //        General Purpose Components
 //==============================================================================================